@@ -0,0 +1,200 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::{
+    BoxedWebSocket, ConnectionConfig, ConnectionConfigBuilder, InferenceSession, PetalsError, RetryPolicy, SessionConfig,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Routing {
+    /// Smooth weighted round-robin, as in nginx: each endpoint gets its share of new sessions
+    /// without long runs on the heaviest one.
+    #[default]
+    RoundRobin,
+    /// Lowest session-open latency (exponentially averaged) divided by weight; endpoints that
+    /// have not been measured yet go first.
+    LeastLatency,
+}
+
+struct Endpoint {
+    connection: ConnectionConfig,
+    weight: u32,
+    current_weight: i64,
+    latency: Option<Duration>,
+    failures: u32,
+    unhealthy_until: Option<Instant>,
+}
+
+impl Endpoint {
+    fn is_healthy(&self, now: Instant) -> bool {
+        self.unhealthy_until.is_none_or(|until| until <= now)
+    }
+
+    fn score(&self) -> f64 {
+        self.latency.map_or(0.0, |latency| latency.as_secs_f64() / self.weight as f64)
+    }
+}
+
+#[derive(Clone)]
+pub struct MultiEndpointClient {
+    endpoints: Arc<Mutex<Vec<Endpoint>>>,
+    routing: Routing,
+    failure_threshold: u32,
+    cooldown: Duration,
+}
+
+impl MultiEndpointClient {
+    pub fn builder() -> MultiEndpointClientBuilder {
+        MultiEndpointClientBuilder::new()
+    }
+
+    /// Opens a session on the endpoint picked by the routing strategy, failing over to the
+    /// others in turn. Unhealthy endpoints are only tried when every endpoint is unhealthy.
+    /// Failover takes the place of retries: each endpoint gets a single attempt, whatever the
+    /// config's retry policy says, so a dead gateway is skipped right away.
+    pub async fn open(&self, config: &SessionConfig) -> Result<InferenceSession<BoxedWebSocket>, PetalsError> {
+        let mut config = config.clone();
+        config.retry_policy = RetryPolicy::none();
+        let mut last_error = None;
+        for (index, connection) in self.candidates() {
+            let started = Instant::now();
+            match connection.open(&config).await {
+                Ok(session) => {
+                    self.record_success(index, started.elapsed());
+                    return Ok(session);
+                }
+                Err(e) => {
+                    self.record_failure(index);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or(PetalsError::NoEndpoints))
+    }
+
+    pub fn healthy_endpoints(&self) -> Vec<String> {
+        let now = Instant::now();
+        let endpoints = self.endpoints.lock().unwrap();
+        endpoints
+            .iter()
+            .filter(|endpoint| endpoint.is_healthy(now))
+            .map(|endpoint| endpoint.connection.url().to_owned())
+            .collect()
+    }
+
+    fn candidates(&self) -> Vec<(usize, ConnectionConfig)> {
+        let now = Instant::now();
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let mut order: Vec<usize> = (0..endpoints.len()).filter(|&i| endpoints[i].is_healthy(now)).collect();
+        if order.is_empty() {
+            order = (0..endpoints.len()).collect();
+        }
+        match self.routing {
+            Routing::RoundRobin if !order.is_empty() => {
+                let total: i64 = order.iter().map(|&i| endpoints[i].weight as i64).sum();
+                for &i in &order {
+                    endpoints[i].current_weight += endpoints[i].weight as i64;
+                }
+                let (position, &first) =
+                    order.iter().enumerate().max_by_key(|&(_, &i)| (endpoints[i].current_weight, usize::MAX - i)).unwrap();
+                endpoints[first].current_weight -= total;
+                order.rotate_left(position);
+            }
+            Routing::RoundRobin => {}
+            Routing::LeastLatency => {
+                order.sort_by(|&a, &b| endpoints[a].score().total_cmp(&endpoints[b].score()));
+            }
+        }
+        order.into_iter().map(|i| (i, endpoints[i].connection.clone())).collect()
+    }
+
+    fn record_success(&self, index: usize, latency: Duration) {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        if let Some(endpoint) = endpoints.get_mut(index) {
+            endpoint.latency = Some(match endpoint.latency {
+                Some(average) => average.mul_f64(0.7) + latency.mul_f64(0.3),
+                None => latency,
+            });
+            endpoint.failures = 0;
+            endpoint.unhealthy_until = None;
+        }
+    }
+
+    fn record_failure(&self, index: usize) {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        if let Some(endpoint) = endpoints.get_mut(index) {
+            endpoint.failures += 1;
+            if endpoint.failures >= self.failure_threshold {
+                endpoint.unhealthy_until = Some(Instant::now() + self.cooldown);
+            }
+        }
+    }
+}
+
+pub struct MultiEndpointClientBuilder {
+    endpoints: Vec<Endpoint>,
+    routing: Routing,
+    failure_threshold: u32,
+    cooldown: Duration,
+}
+
+impl Default for MultiEndpointClientBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MultiEndpointClientBuilder {
+    pub fn new() -> Self {
+        Self {
+            endpoints: Vec::new(),
+            routing: Routing::default(),
+            failure_threshold: 3,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+
+    pub fn endpoint(self, url: &str, weight: u32) -> Self {
+        self.connection(ConnectionConfigBuilder::new(url).build(), weight)
+    }
+
+    /// An endpoint with its own headers, credentials, TLS connector or proxy.
+    pub fn connection(mut self, connection: ConnectionConfig, weight: u32) -> Self {
+        self.endpoints.push(Endpoint {
+            connection,
+            weight: weight.max(1),
+            current_weight: 0,
+            latency: None,
+            failures: 0,
+            unhealthy_until: None,
+        });
+        self
+    }
+
+    pub fn routing(mut self, routing: Routing) -> Self {
+        self.routing = routing;
+        self
+    }
+
+    /// Consecutive failed opens after which an endpoint is skipped for `cooldown`.
+    pub fn failure_threshold(mut self, failure_threshold: u32) -> Self {
+        self.failure_threshold = failure_threshold.max(1);
+        self
+    }
+
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    pub fn build(self) -> MultiEndpointClient {
+        MultiEndpointClient {
+            endpoints: Arc::new(Mutex::new(self.endpoints)),
+            routing: self.routing,
+            failure_threshold: self.failure_threshold,
+            cooldown: self.cooldown,
+        }
+    }
+}
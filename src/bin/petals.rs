@@ -0,0 +1,237 @@
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+use futures_util::StreamExt;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use websocket_petals_api::{ChatSession, GenerateParams, GenerateParamsBuilder, InferenceSession, Model, ParamsError};
+
+const USAGE: &str = "\
+Usage: petals --url <URL> [OPTIONS] [PROMPT]...
+       petals chat --url <URL> [OPTIONS]
+
+Sends PROMPT (or stdin when no PROMPT is given) to a Petals WebSocket endpoint
+and prints the generated tokens as they arrive.
+
+The `chat` command keeps a single inference session open and sends every line
+read from stdin as a new turn of the conversation.
+
+Options:
+  --url <URL>              WebSocket endpoint, e.g. ws://localhost:5000/api/v2/generate
+  --model <MODEL>          Model repository id, e.g. stabilityai/StableBeluga2
+  --max-length <N>         Maximum session length in tokens [default: 512]
+  --max-new-tokens <N>     Number of tokens generated per step
+  --temperature <T>        Sampling temperature (enables sampling)
+  --top-k <K>              Top-k sampling (enables sampling)
+  --top-p <P>              Nucleus sampling (enables sampling)
+  --stop-sequence <TEXT>   Stop generating once TEXT is produced (repeatable)
+  -h, --help               Print this help";
+
+enum Command {
+    Generate,
+    Chat,
+}
+
+struct Args {
+    command: Command,
+    url: String,
+    model: Option<Model>,
+    max_length: u32,
+    max_new_tokens: Option<u32>,
+    temperature: Option<f32>,
+    top_k: Option<u32>,
+    top_p: Option<f32>,
+    stop_sequences: Vec<String>,
+    prompt: Vec<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut url = None;
+    let mut parsed = Args {
+        command: Command::Generate,
+        url: String::new(),
+        model: None,
+        max_length: 512,
+        max_new_tokens: None,
+        temperature: None,
+        top_k: None,
+        top_p: None,
+        stop_sequences: Vec::new(),
+        prompt: Vec::new(),
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("missing value for `{arg}`"));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--url" => url = Some(value()?),
+            "--model" => parsed.model = Some(Model::from(value()?.as_str())),
+            "--max-length" => parsed.max_length = parse_number(&arg, &value()?)?,
+            "--max-new-tokens" => parsed.max_new_tokens = Some(parse_number(&arg, &value()?)?),
+            "--temperature" => parsed.temperature = Some(parse_number(&arg, &value()?)?),
+            "--top-k" => parsed.top_k = Some(parse_number(&arg, &value()?)?),
+            "--top-p" => parsed.top_p = Some(parse_number(&arg, &value()?)?),
+            "--stop-sequence" => parsed.stop_sequences.push(value()?),
+            "chat" if parsed.prompt.is_empty() && matches!(parsed.command, Command::Generate) => {
+                parsed.command = Command::Chat
+            }
+            "--" => parsed.prompt.extend(args.by_ref()),
+            flag if flag.starts_with("--") => return Err(format!("unknown option `{flag}`")),
+            _ => parsed.prompt.push(arg),
+        }
+    }
+    parsed.url = url.ok_or("missing required option `--url`")?;
+    if matches!(parsed.command, Command::Chat) && !parsed.prompt.is_empty() {
+        return Err("`chat` reads its turns from stdin and takes no PROMPT".to_owned());
+    }
+    Ok(Some(parsed))
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value `{value}` for `{flag}`"))
+}
+
+impl Args {
+    fn params(&self, inputs: String) -> Result<GenerateParams, ParamsError> {
+        let mut builder = GenerateParamsBuilder::new().inputs(inputs).max_length(self.max_length);
+        if let Some(model) = self.model.clone() {
+            builder = builder.model(model);
+        }
+        if let Some(max_new_tokens) = self.max_new_tokens {
+            builder = builder.max_new_tokens(max_new_tokens);
+        }
+        if self.temperature.is_some() || self.top_k.is_some() || self.top_p.is_some() {
+            builder = builder.do_sample(true);
+        }
+        if let Some(temperature) = self.temperature {
+            builder = builder.temperature(temperature);
+        }
+        if let Some(top_k) = self.top_k {
+            builder = builder.top_k(top_k);
+        }
+        if let Some(top_p) = self.top_p {
+            builder = builder.top_p(top_p);
+        }
+        if !self.stop_sequences.is_empty() {
+            builder = builder.stop_sequences(self.stop_sequences.clone());
+        }
+        builder.build_for_session(self.max_length)
+    }
+}
+
+async fn run(args: Args) -> Result<(), Box<dyn std::error::Error>> {
+    let prompt = if args.prompt.is_empty() {
+        let mut prompt = String::new();
+        io::stdin().read_to_string(&mut prompt)?;
+        prompt
+    } else {
+        args.prompt.join(" ")
+    };
+    let params = args.params(prompt)?;
+
+    let mut session = InferenceSession::open(args.url, args.max_length, args.model).await?;
+    let mut steps = Box::pin(session.generate(params).await?);
+    let mut stdout = io::stdout();
+    while let Some(response) = steps.next().await {
+        write!(stdout, "{}", response?.outputs)?;
+        stdout.flush()?;
+    }
+    writeln!(stdout)?;
+    Ok(())
+}
+
+async fn run_chat(args: Args) -> Result<(), Box<dyn std::error::Error>> {
+    let mut chat = ChatSession::open(args.url.clone(), args.max_length, args.model.clone()).await?;
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = io::stdout();
+    loop {
+        write!(stdout, "[{}/{}] > ", chat.used_length(), chat.max_length())?;
+        stdout.flush()?;
+        let Some(line) = lines.next_line().await? else {
+            writeln!(stdout)?;
+            return Ok(());
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut steps = Box::pin(chat.send(args.params(line)?).await?);
+        while let Some(response) = steps.next().await {
+            write!(stdout, "{}", response?.outputs)?;
+            stdout.flush()?;
+        }
+        writeln!(stdout)?;
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    let result = match args.command {
+        Command::Generate => run(args).await,
+        Command::Chat => run_chat(args).await,
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn detects_chat_command() {
+        let args = parse(&["chat", "--url", "ws://localhost"]).unwrap().unwrap();
+        assert!(matches!(args.command, Command::Chat));
+
+        let args = parse(&["--url", "ws://localhost", "let's", "chat"]).unwrap().unwrap();
+        assert!(matches!(args.command, Command::Generate));
+        assert_eq!(args.prompt, ["let's", "chat"]);
+
+        let e = parse(&["chat", "--url", "ws://localhost", "hello"]).err().unwrap();
+        assert!(e.contains("takes no PROMPT"));
+    }
+
+    #[test]
+    fn passes_arguments_after_double_dash_through() {
+        let args = parse(&["--url", "ws://localhost", "--", "--top-k", "chat"]).unwrap().unwrap();
+        assert!(matches!(args.command, Command::Generate));
+        assert_eq!(args.prompt, ["--top-k", "chat"]);
+        assert_eq!(args.top_k, None);
+    }
+
+    #[test]
+    fn requires_url() {
+        assert_eq!(parse(&["hello"]).err().unwrap(), "missing required option `--url`");
+        assert_eq!(parse(&["--url"]).err().unwrap(), "missing value for `--url`");
+        assert!(parse(&["--help"]).unwrap().is_none());
+    }
+
+    #[test]
+    fn collects_repeated_stop_sequences() {
+        let args = parse(&["--url", "ws://localhost", "--stop-sequence", "###", "--stop-sequence", "</s>", "hi"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(args.stop_sequences, ["###", "</s>"]);
+
+        let params = args.params("hi".to_owned()).unwrap();
+        assert_eq!(params.stop_sequence(), Some("###"));
+        assert_eq!(params.stop_sequences(), ["###", "</s>"]);
+    }
+}
@@ -0,0 +1,43 @@
+use futures_util::Stream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+use crate::{GenerateParams, InferenceSession, Model, PetalsError, Response};
+
+/// Each turn is sent on its own: the server keeps the earlier ones in the session's attention
+/// cache, and the session tracks how much of `max_length` the conversation has used.
+pub struct ChatSession {
+    session: InferenceSession,
+}
+
+impl ChatSession {
+    pub async fn open<U>(url: U, max_length: u32, model: Option<Model>) -> Result<Self, PetalsError>
+    where
+        U: IntoClientRequest + Unpin,
+    {
+        let session = InferenceSession::open(url, max_length, model).await?;
+        Ok(Self::from_session(session))
+    }
+
+    pub fn from_session(session: InferenceSession) -> Self {
+        Self { session }
+    }
+
+    pub fn max_length(&self) -> u32 {
+        self.session.max_length()
+    }
+
+    pub fn used_length(&self) -> u32 {
+        self.session.used_length()
+    }
+
+    pub fn remaining_length(&self) -> u32 {
+        self.session.remaining_length()
+    }
+
+    pub async fn send(
+        &mut self,
+        params: GenerateParams,
+    ) -> Result<impl Stream<Item = Result<Response, PetalsError>> + '_, PetalsError> {
+        self.session.generate(params).await
+    }
+}
@@ -0,0 +1,119 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::tokens::SharedCounter;
+use crate::{Model, PetalsError, RetryPolicy, TimeoutKind, TokenCounter};
+
+#[derive(Clone, Debug)]
+pub struct SessionConfig {
+    pub(crate) max_length: u32,
+    pub(crate) model: Option<Model>,
+    pub(crate) retry_policy: RetryPolicy,
+    pub(crate) connect_timeout: Option<Duration>,
+    pub(crate) handshake_timeout: Option<Duration>,
+    pub(crate) first_token_timeout: Option<Duration>,
+    pub(crate) inter_token_timeout: Option<Duration>,
+    pub(crate) token_counter: SharedCounter,
+}
+
+impl SessionConfig {
+    pub fn max_length(&self) -> u32 {
+        self.max_length
+    }
+
+    pub fn model(&self) -> Option<&Model> {
+        self.model.as_ref()
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    pub fn connect_timeout(&self) -> Option<Duration> {
+        self.connect_timeout
+    }
+
+    pub fn handshake_timeout(&self) -> Option<Duration> {
+        self.handshake_timeout
+    }
+
+    pub fn first_token_timeout(&self) -> Option<Duration> {
+        self.first_token_timeout
+    }
+
+    pub fn inter_token_timeout(&self) -> Option<Duration> {
+        self.inter_token_timeout
+    }
+
+    pub fn token_counter(&self) -> &dyn TokenCounter {
+        &*self.token_counter.0
+    }
+}
+
+pub struct SessionConfigBuilder(SessionConfig);
+
+impl SessionConfigBuilder {
+    pub fn new(max_length: u32) -> Self {
+        Self(SessionConfig {
+            max_length,
+            model: None,
+            retry_policy: RetryPolicy::default(),
+            connect_timeout: None,
+            handshake_timeout: None,
+            first_token_timeout: None,
+            inter_token_timeout: None,
+            token_counter: SharedCounter::default(),
+        })
+    }
+
+    pub fn model(mut self, model: Model) -> Self {
+        self.0.model = Some(model);
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.0.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.0.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.0.handshake_timeout = Some(timeout);
+        self
+    }
+
+    pub fn first_token_timeout(mut self, timeout: Duration) -> Self {
+        self.0.first_token_timeout = Some(timeout);
+        self
+    }
+
+    pub fn inter_token_timeout(mut self, timeout: Duration) -> Self {
+        self.0.inter_token_timeout = Some(timeout);
+        self
+    }
+
+    pub fn token_counter(mut self, token_counter: impl TokenCounter + 'static) -> Self {
+        self.0.token_counter = SharedCounter(Arc::new(token_counter));
+        self
+    }
+
+    pub fn build(self) -> SessionConfig {
+        self.0
+    }
+}
+
+pub(crate) async fn with_timeout<F: Future>(
+    timeout: Option<Duration>,
+    kind: TimeoutKind,
+    future: F,
+) -> Result<F::Output, PetalsError> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, future).await.map_err(|_| PetalsError::Timeout(kind)),
+        None => Ok(future.await),
+    }
+}
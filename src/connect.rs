@@ -0,0 +1,184 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::client::Request;
+use tokio_tungstenite::tungstenite::http::{HeaderName, HeaderValue};
+use tokio_tungstenite::tungstenite::{self, error::UrlError};
+use tokio_tungstenite::{client_async, WebSocketStream};
+
+use crate::{with_timeout, InferenceSession, PetalsError, Proxy, SessionConfig, TimeoutKind};
+
+pub trait Io: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T> Io for T where T: AsyncRead + AsyncWrite + Unpin + Send {}
+
+pub type BoxedWebSocket = WebSocketStream<Box<dyn Io>>;
+
+pub type TlsConnect<'a> = Pin<Box<dyn Future<Output = io::Result<Box<dyn Io>>> + Send + 'a>>;
+
+/// TLS layer for wss:// connections. The `rustls` and `native-tls` features provide
+/// `RustlsConnector` and `NativeTlsConnector`, one of which is used when none is configured;
+/// implement it to plug in another stack or custom roots and client certificates.
+pub trait TlsConnector: Send + Sync {
+    fn connect(&self, server_name: String, stream: TcpStream) -> TlsConnect<'_>;
+}
+
+#[derive(Clone)]
+pub struct ConnectionConfig {
+    url: String,
+    headers: Vec<(String, String)>,
+    subprotocols: Vec<String>,
+    tls_connector: Option<Arc<dyn TlsConnector>>,
+    server_name: Option<String>,
+    proxy: Option<Proxy>,
+    proxy_from_env: bool,
+}
+
+impl ConnectionConfig {
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub async fn connect(&self) -> Result<BoxedWebSocket, PetalsError> {
+        let request = self.request()?;
+        let uri = request.uri().clone();
+        let tls = match uri.scheme_str() {
+            Some("ws") => false,
+            Some("wss") => true,
+            _ => return Err(tungstenite::Error::Url(UrlError::UnsupportedUrlScheme).into()),
+        };
+        let host = uri.host().ok_or(tungstenite::Error::Url(UrlError::NoHostName))?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let port = uri.port_u16().unwrap_or(if tls { 443 } else { 80 });
+        let proxy = match &self.proxy {
+            Some(proxy) => Some(proxy.clone()),
+            None if self.proxy_from_env => Proxy::from_env(tls, host)?,
+            None => None,
+        };
+        let stream = dial(host, port, proxy.as_ref()).await?;
+
+        let stream: Box<dyn Io> = if tls {
+            let default_connector;
+            let connector = match &self.tls_connector {
+                Some(connector) => connector.as_ref(),
+                None => {
+                    default_connector =
+                        default_tls_connector().ok_or(tungstenite::Error::Url(UrlError::TlsFeatureNotEnabled))?;
+                    default_connector.as_ref()
+                }
+            };
+            let server_name = self.server_name.clone().unwrap_or_else(|| host.to_owned());
+            connector.connect(server_name, stream).await.map_err(tungstenite::Error::Io)?
+        } else {
+            Box::new(stream)
+        };
+        let (ws_stream, _) = client_async(request, stream).await?;
+        Ok(ws_stream)
+    }
+
+    pub async fn open(&self, config: &SessionConfig) -> Result<InferenceSession<BoxedWebSocket>, PetalsError> {
+        config
+            .retry_policy
+            .retry(|| async {
+                let ws_stream = with_timeout(config.connect_timeout, TimeoutKind::Connect, self.connect()).await??;
+                InferenceSession::from_stream_with(ws_stream, config).await
+            })
+            .await
+    }
+
+    fn request(&self) -> Result<Request, tungstenite::Error> {
+        let mut request = self.url.as_str().into_client_request()?;
+        let headers = request.headers_mut();
+        for (name, value) in &self.headers {
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(tungstenite::http::Error::from)?;
+            let value = HeaderValue::from_str(value).map_err(tungstenite::http::Error::from)?;
+            headers.append(name, value);
+        }
+        if !self.subprotocols.is_empty() {
+            let value = HeaderValue::from_str(&self.subprotocols.join(", ")).map_err(tungstenite::http::Error::from)?;
+            headers.insert("Sec-WebSocket-Protocol", value);
+        }
+        Ok(request)
+    }
+}
+
+// Used for wss:// when no connector was configured: rustls if enabled, otherwise native-tls.
+fn default_tls_connector() -> Option<Box<dyn TlsConnector>> {
+    #[cfg(feature = "rustls")]
+    return Some(Box::new(crate::RustlsConnector::new()));
+    #[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+    return crate::NativeTlsConnector::new().ok().map(|connector| Box::new(connector) as Box<dyn TlsConnector>);
+    #[cfg(not(any(feature = "rustls", feature = "native-tls")))]
+    None
+}
+
+pub(crate) async fn dial(host: &str, port: u16, proxy: Option<&Proxy>) -> Result<TcpStream, PetalsError> {
+    let stream = match proxy {
+        Some(proxy) => proxy.connect(host, port).await,
+        None => TcpStream::connect((host, port)).await,
+    };
+    Ok(stream.map_err(tungstenite::Error::Io)?)
+}
+
+pub struct ConnectionConfigBuilder(ConnectionConfig);
+
+impl ConnectionConfigBuilder {
+    pub fn new(url: &str) -> Self {
+        Self(ConnectionConfig {
+            url: url.to_owned(),
+            headers: Vec::new(),
+            subprotocols: Vec::new(),
+            tls_connector: None,
+            server_name: None,
+            proxy: None,
+            proxy_from_env: false,
+        })
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.0.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    pub fn bearer_token(self, token: &str) -> Self {
+        self.header("Authorization", &format!("Bearer {token}"))
+    }
+
+    pub fn subprotocol(mut self, subprotocol: &str) -> Self {
+        self.0.subprotocols.push(subprotocol.to_owned());
+        self
+    }
+
+    pub fn tls_connector(mut self, tls_connector: impl TlsConnector + 'static) -> Self {
+        self.0.tls_connector = Some(Arc::new(tls_connector));
+        self
+    }
+
+    /// Name presented in the TLS handshake when it differs from the host in the URL,
+    /// e.g. when connecting to a gateway by IP address.
+    pub fn server_name(mut self, server_name: &str) -> Self {
+        self.0.server_name = Some(server_name.to_owned());
+        self
+    }
+
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.0.proxy = Some(proxy);
+        self
+    }
+
+    /// Picks the proxy up from `HTTPS_PROXY`/`HTTP_PROXY`/`ALL_PROXY` (honouring `NO_PROXY`)
+    /// unless one was set explicitly. Connecting fails if the variable holds an unusable proxy.
+    pub fn proxy_from_env(mut self) -> Self {
+        self.0.proxy_from_env = true;
+        self
+    }
+
+    pub fn build(self) -> ConnectionConfig {
+        self.0
+    }
+}
@@ -0,0 +1,118 @@
+use std::fmt;
+
+use tokio_tungstenite::tungstenite::{self, protocol::Message};
+
+#[derive(Debug)]
+pub enum PetalsError {
+    TungsteniteError(tungstenite::Error),
+    ConnectionClosed,
+    UnexpectedMessage(Message),
+    JsonError(serde_json::Error),
+    MissingField(&'static str),
+    ApiError { traceback: String },
+    BudgetExceeded { requested: u32, remaining: u32 },
+    Timeout(TimeoutKind),
+    NoEndpoints,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeoutKind {
+    Connect,
+    Handshake,
+    FirstToken,
+    InterToken,
+    Deadline,
+}
+
+pub type OpenInferenceSessionError = PetalsError;
+
+impl PetalsError {
+    pub fn is_disconnect(&self) -> bool {
+        match self {
+            Self::ConnectionClosed => true,
+            Self::TungsteniteError(e) => matches!(
+                e,
+                tungstenite::Error::ConnectionClosed
+                    | tungstenite::Error::AlreadyClosed
+                    | tungstenite::Error::Io(_)
+                    | tungstenite::Error::Protocol(_)
+            ),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for PetalsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TungsteniteError(e) => write!(f, "websocket error: {e}"),
+            Self::ConnectionClosed => write!(f, "connection closed by the server"),
+            Self::UnexpectedMessage(message) => write!(f, "unexpected websocket message: {message:?}"),
+            Self::JsonError(e) => write!(f, "failed to decode server reply: {e}"),
+            Self::MissingField(field) => write!(f, "server reply is missing the `{field}` field"),
+            Self::ApiError { traceback } => write!(f, "server error: {traceback}"),
+            Self::BudgetExceeded { requested, remaining } => {
+                write!(f, "request needs {requested} tokens but only {remaining} remain in the session")
+            }
+            Self::Timeout(kind) => match kind {
+                TimeoutKind::Connect => write!(f, "timed out connecting to the server"),
+                TimeoutKind::Handshake => write!(f, "timed out opening the inference session"),
+                TimeoutKind::FirstToken => write!(f, "timed out waiting for the first token"),
+                TimeoutKind::InterToken => write!(f, "timed out waiting for the next token"),
+                TimeoutKind::Deadline => write!(f, "retry deadline reached"),
+            },
+            Self::NoEndpoints => write!(f, "no endpoints configured"),
+        }
+    }
+}
+
+impl std::error::Error for PetalsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::TungsteniteError(e) => Some(e),
+            Self::JsonError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<tungstenite::Error> for PetalsError {
+    fn from(e: tungstenite::Error) -> Self {
+        Self::TungsteniteError(e)
+    }
+}
+
+impl From<serde_json::Error> for PetalsError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::JsonError(e)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParamsError {
+    MissingLength,
+    InvalidTemperature(f32),
+    InvalidTopP(f32),
+    InvalidTopK,
+    MaxLengthExceeded { max_length: u32, session_max_length: u32 },
+    EmptyStopSequence,
+}
+
+impl fmt::Display for ParamsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingLength => write!(f, "either max_length or max_new_tokens must be set"),
+            Self::InvalidTemperature(temperature) => {
+                write!(f, "temperature must be greater than 0 when sampling, got {temperature}")
+            }
+            Self::InvalidTopP(top_p) => write!(f, "top_p must be in (0, 1], got {top_p}"),
+            Self::InvalidTopK => write!(f, "top_k must be greater than 0"),
+            Self::MaxLengthExceeded { max_length, session_max_length } => {
+                write!(f, "max_length {max_length} exceeds the session max_length {session_max_length}")
+            }
+            Self::EmptyStopSequence => write!(f, "stop_sequence must not be empty"),
+        }
+    }
+}
+
+impl std::error::Error for ParamsError {}
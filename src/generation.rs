@@ -0,0 +1,81 @@
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_util::Stream;
+use tokio::sync::Notify;
+
+use crate::{PetalsError, Response};
+
+#[derive(Debug, Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+/// Cancellation is permanent: a handle cannot be reset, and passing a cancelled handle to
+/// `generate_with_cancel` yields an empty generation without sending anything. A handle may be
+/// shared by generations running at the same time; cancelling it stops all of them.
+#[derive(Clone, Debug, Default)]
+pub struct CancelHandle(Arc<CancelState>);
+
+impl CancelHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        self.0.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub(crate) async fn cancelled(&self) {
+        // Register before checking the flag: `notify_waiters` only wakes futures already waiting.
+        let notified = self.0.notify.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+        if !self.is_cancelled() {
+            notified.await;
+        }
+    }
+}
+
+/// Stream of generation steps. Cancelling it (or dropping it early) leaves the remaining
+/// frames of the generation on the socket; the session discards them before its next request.
+pub struct Generation<'a> {
+    steps: Pin<Box<dyn Stream<Item = Result<Response, PetalsError>> + Send + 'a>>,
+    cancel: CancelHandle,
+}
+
+impl<'a> Generation<'a> {
+    pub(crate) fn new<S>(steps: S, cancel: CancelHandle) -> Self
+    where
+        S: Stream<Item = Result<Response, PetalsError>> + Send + 'a,
+    {
+        Self {
+            steps: Box::pin(steps),
+            cancel,
+        }
+    }
+
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+}
+
+impl Stream for Generation<'_> {
+    type Item = Result<Response, PetalsError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.steps.as_mut().poll_next(cx)
+    }
+}
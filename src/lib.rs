@@ -1,124 +1,366 @@
-use futures_util::{SinkExt, StreamExt};
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use std::fmt;
+use std::time::Duration;
+
+use futures_util::{stream, FutureExt, Sink, SinkExt, Stream, StreamExt};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use tokio::net::TcpStream;
-use tokio_tungstenite::tungstenite::client::IntoClientRequest;
-use tokio_tungstenite::{connect_async, MaybeTlsStream};
-use tokio_tungstenite::{tungstenite::protocol::Message, WebSocketStream};
+use tokio_tungstenite::tungstenite::{self, client::IntoClientRequest, protocol::Message};
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+mod balancer;
+mod chat;
+mod config;
+mod connect;
+mod error;
+mod generation;
+mod pool;
+mod proxy;
+pub mod protocol;
+mod resilient;
+mod retry;
+mod sampling;
+mod stop;
+mod template;
+mod tokens;
+#[cfg(any(feature = "rustls", feature = "native-tls"))]
+mod tls;
+#[cfg(feature = "testing")]
+pub mod testing;
+
+pub use balancer::{MultiEndpointClient, MultiEndpointClientBuilder, Routing};
+pub use chat::ChatSession;
+pub use config::{SessionConfig, SessionConfigBuilder};
+pub use connect::{BoxedWebSocket, ConnectionConfig, ConnectionConfigBuilder, Io, TlsConnect, TlsConnector};
+pub use error::{OpenInferenceSessionError, ParamsError, PetalsError, TimeoutKind};
+pub use generation::{CancelHandle, Generation};
+pub use proxy::{Proxy, ProxyKind};
+pub use pool::{PooledSession, SessionPool, SessionPoolBuilder};
+pub use protocol::{GenerateRequest, OpenSessionRequest, RequestType, Response};
+pub use resilient::ResilientSession;
+pub use retry::{RetryPolicy, RetryPolicyBuilder};
+pub use sampling::SamplingConfig;
+pub use tokens::{CharEstimate, TokenCounter};
+#[cfg(feature = "native-tls")]
+pub use tls::{native_tls, NativeTlsConnector};
+#[cfg(feature = "rustls")]
+pub use tls::{rustls, RustlsConnector};
+pub use template::{ChatMessage, ChatTemplate, Guanaco, Llama2Chat, PlainTranscript, Role, StableBeluga};
+
+use config::with_timeout;
+use protocol::{ClientMessage, ServerMessage};
+use stop::enforce_stop_sequences;
+use tokens::SharedCounter;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Model {
-    #[serde(rename = "meta-llama/Llama-2-70b-chat-hf")]
     Llama2_70bChatHf,
-    #[serde(rename = "stabilityai/StableBeluga2")]
     StableBeluga2,
-    #[serde(rename = "timdettmers/guanaco-65b")]
     Guanaco65b,
-    #[serde(rename = "enoch/llama-65b-hf")]
     Llama65bHf,
-    #[serde(rename = "bigscience/bloomz")]
     Bloomz,
+    Custom(String),
 }
 
-#[derive(Serialize)]
-struct OpenSessionRequest {
-    #[serde(rename = "type")]
-    request_type: RequestType,
-    max_length: u32,
-    model: Option<Model>,
+impl Model {
+    pub fn repo_id(&self) -> &str {
+        match self {
+            Self::Llama2_70bChatHf => "meta-llama/Llama-2-70b-chat-hf",
+            Self::StableBeluga2 => "stabilityai/StableBeluga2",
+            Self::Guanaco65b => "timdettmers/guanaco-65b",
+            Self::Llama65bHf => "enoch/llama-65b-hf",
+            Self::Bloomz => "bigscience/bloomz",
+            Self::Custom(repo_id) => repo_id,
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize)]
-struct GenerateRequest {
-    #[serde(rename = "type")]
-    request_type: RequestType,
-    model: Option<Model>,
-    max_length: Option<u32>,
-    inputs: Option<String>,
-    stop_sequence: Option<String>,
-    do_sample: Option<bool>,
-    temperature: Option<f32>,
-    top_k: Option<u32>,
-    top_p: Option<f32>,
-    max_new_tokens: Option<u32>,
+impl From<&str> for Model {
+    fn from(repo_id: &str) -> Self {
+        match repo_id {
+            "meta-llama/Llama-2-70b-chat-hf" => Self::Llama2_70bChatHf,
+            "stabilityai/StableBeluga2" => Self::StableBeluga2,
+            "timdettmers/guanaco-65b" => Self::Guanaco65b,
+            "enoch/llama-65b-hf" => Self::Llama65bHf,
+            "bigscience/bloomz" => Self::Bloomz,
+            repo_id => Self::Custom(repo_id.to_owned()),
+        }
+    }
+}
+
+impl fmt::Display for Model {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.repo_id())
+    }
 }
 
-#[derive(Clone, Serialize, Deserialize)]
-enum RequestType {
-    #[serde(rename = "generate")]
-    Generate,
-    #[serde(rename = "open_inference_session")]
-    OpenInferenceSession,
+impl Serialize for Model {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.repo_id())
+    }
 }
 
-#[derive(Debug, Deserialize)]
-pub struct Response {
-    pub ok: bool,
-    pub outputs: String,
-    pub stop: bool,
+impl<'de> Deserialize<'de> for Model {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repo_id = String::deserialize(deserializer)?;
+        Ok(Self::from(repo_id.as_str()))
+    }
 }
 
-#[derive(Debug)]
-pub enum OpenInferenceSessionError {
-    TungsteniteError(tokio_tungstenite::tungstenite::Error),
-    ApiError { traceback: String },
+pub trait Transport:
+    Sink<Message, Error = tungstenite::Error> + Stream<Item = Result<Message, tungstenite::Error>> + Unpin + Send
+{
 }
 
-pub struct InferenceSession {
-    ws_stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+impl<S> Transport for S where
+    S: Sink<Message, Error = tungstenite::Error> + Stream<Item = Result<Message, tungstenite::Error>> + Unpin + Send
+{
+}
+
+pub struct InferenceSession<S = WebSocketStream<MaybeTlsStream<TcpStream>>> {
+    ws_stream: S,
+    first_token_timeout: Option<Duration>,
+    inter_token_timeout: Option<Duration>,
+    pending: bool,
+    failed: bool,
+    max_length: u32,
+    used_length: u32,
+    step_tokens: Option<u32>,
+    token_counter: SharedCounter,
 }
 
 impl InferenceSession {
-    pub async fn open<U>(url: U, max_length: u32, model: Option<Model>) -> Result<Self, OpenInferenceSessionError>
+    pub async fn open<U>(url: U, max_length: u32, model: Option<Model>) -> Result<Self, PetalsError>
     where
         U: IntoClientRequest + Unpin,
     {
-        let (mut ws_stream, _) = connect_async(url)
-            .await
-            .map_err(|e| OpenInferenceSessionError::TungsteniteError(e))?;
+        let mut config = SessionConfigBuilder::new(max_length);
+        if let Some(model) = model {
+            config = config.model(model);
+        }
+        Self::connect(url, &config.build()).await
+    }
+
+    pub async fn open_with<U>(url: U, config: &SessionConfig) -> Result<Self, PetalsError>
+    where
+        U: IntoClientRequest + Clone + Unpin,
+    {
+        config.retry_policy.retry(|| Self::connect(url.clone(), config)).await
+    }
+
+    async fn connect<U>(url: U, config: &SessionConfig) -> Result<Self, PetalsError>
+    where
+        U: IntoClientRequest + Unpin,
+    {
+        let (ws_stream, _) = with_timeout(config.connect_timeout, TimeoutKind::Connect, connect_async(url)).await??;
+        Self::from_stream_with(ws_stream, config).await
+    }
+}
+
+impl<S: Transport> InferenceSession<S> {
+    pub async fn from_stream(stream: S, max_length: u32, model: Option<Model>) -> Result<Self, PetalsError> {
+        let mut config = SessionConfigBuilder::new(max_length);
+        if let Some(model) = model {
+            config = config.model(model);
+        }
+        Self::from_stream_with(stream, &config.build()).await
+    }
+
+    pub async fn from_stream_with(stream: S, config: &SessionConfig) -> Result<Self, PetalsError> {
+        let mut session = Self {
+            ws_stream: stream,
+            first_token_timeout: config.first_token_timeout,
+            inter_token_timeout: config.inter_token_timeout,
+            pending: false,
+            failed: false,
+            max_length: config.max_length,
+            used_length: 0,
+            step_tokens: None,
+            token_counter: config.token_counter.clone(),
+        };
+        let handshake = session.open_inference_session(config.max_length, config.model.clone());
+        with_timeout(config.handshake_timeout, TimeoutKind::Handshake, handshake).await??;
+        Ok(session)
+    }
+
+    async fn open_inference_session(&mut self, max_length: u32, model: Option<Model>) -> Result<(), PetalsError> {
         let request = OpenSessionRequest {
             request_type: RequestType::OpenInferenceSession,
             model,
             max_length,
         };
-        let open_session_request_str = serde_json::to_string(&request).unwrap();
-        ws_stream
-            .send(Message::Text(open_session_request_str))
-            .await
-            .map_err(|e| OpenInferenceSessionError::TungsteniteError(e))?;
-        let message = ws_stream.next().await.unwrap().unwrap();
-        let message = message.to_text().unwrap();
-        let response: Value = serde_json::from_str(message).unwrap();
-        if response.get("ok").unwrap() == "false" {
-            let traceback = response.get("traceback").unwrap().to_string();
-            return Err(OpenInferenceSessionError::ApiError { traceback });
-        }
-        Ok(Self {
-            ws_stream
-        })
+        self.send_message(&ClientMessage::OpenInferenceSession(request)).await?;
+        let message = next_text(&mut self.ws_stream).await?;
+        match ServerMessage::decode(&message)? {
+            ServerMessage::Error { traceback } => Err(PetalsError::ApiError { traceback }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn max_length(&self) -> u32 {
+        self.max_length
+    }
+
+    /// Tokens taken up so far. Inputs are measured with the session's `TokenCounter`. Each
+    /// generation step counts as `max_new_tokens` tokens when the request sets it, since that is
+    /// what the server generates per step; otherwise (e.g. with only `max_length`, where one step
+    /// can carry the whole completion) the step's outputs are measured with the counter too.
+    /// A step counts as at least one token.
+    pub fn used_length(&self) -> u32 {
+        self.used_length
+    }
+
+    pub fn remaining_length(&self) -> u32 {
+        self.max_length.saturating_sub(self.used_length)
+    }
+
+    pub fn token_counter(&self) -> &dyn TokenCounter {
+        &*self.token_counter.0
+    }
+
+    pub async fn generate(&mut self, params: GenerateParams) -> Result<Generation<'_>, PetalsError> {
+        self.generate_with_cancel(params, CancelHandle::new()).await
+    }
+
+    pub async fn generate_with_cancel(
+        &mut self,
+        params: GenerateParams,
+        cancel: CancelHandle,
+    ) -> Result<Generation<'_>, PetalsError> {
+        if cancel.is_cancelled() {
+            return Ok(Generation::new(stream::empty(), cancel));
+        }
+        let stop_sequences = params.stop_sequences.clone();
+        self.send_generate(&GenerateRequest::from(params)).await?;
+        let steps = stream::unfold(Some((self, true, cancel.clone())), |state| async move {
+            let (session, first, cancel) = state?;
+            let step = tokio::select! {
+                biased;
+                _ = cancel.cancelled() => return None,
+                step = session.next_step(first) => step,
+            };
+            match step {
+                Ok(response) if response.stop => Some((Ok(response), None)),
+                Ok(response) => Some((Ok(response), Some((session, false, cancel)))),
+                Err(e) => Some((Err(e), None)),
+            }
+        });
+        Ok(Generation::new(enforce_stop_sequences(steps, stop_sequences), cancel))
     }
 
-    pub async fn generate(&mut self, params: GenerateParams) -> Result<(), tokio_tungstenite::tungstenite::Error> {
-        let request = GenerateRequest {
-            request_type: RequestType::Generate,
-            model: params.model,
-            max_length: params.max_length,
-            inputs: params.inputs,
-            stop_sequence: params.stop_sequence,
-            do_sample: params.do_sample,
-            temperature: params.temperature,
-            top_k: params.top_k,
-            top_p: params.top_p,
-            max_new_tokens: params.max_new_tokens,
+    pub async fn generate_text(&mut self, params: GenerateParams) -> Result<String, PetalsError> {
+        let mut text = String::new();
+        let mut steps = Box::pin(self.generate(params).await?);
+        while let Some(response) = steps.next().await {
+            text.push_str(&response?.outputs);
+        }
+        Ok(text)
+    }
+
+    /// Cheap liveness check: fails if the server already closed the connection or the ping
+    /// cannot be written. Frames of an abandoned generation are left for `send_generate`.
+    pub async fn ping(&mut self) -> Result<(), PetalsError> {
+        while !self.pending {
+            match self.ws_stream.next().now_or_never() {
+                None => break,
+                Some(Some(Ok(Message::Ping(_) | Message::Pong(_)))) => continue,
+                Some(Some(Ok(Message::Close(_))) | None) => return Err(PetalsError::ConnectionClosed),
+                Some(Some(Ok(message))) => return Err(PetalsError::UnexpectedMessage(message)),
+                Some(Some(Err(e))) => return Err(e.into()),
+            }
+        }
+        self.ws_stream.send(Message::Ping(Vec::new().into())).await?;
+        Ok(())
+    }
+
+    async fn next_step(&mut self, first: bool) -> Result<Response, PetalsError> {
+        let (timeout, kind) = if first {
+            (self.first_token_timeout, TimeoutKind::FirstToken)
+        } else {
+            (self.inter_token_timeout, TimeoutKind::InterToken)
         };
-        let generate_request_str = serde_json::to_string(&request).unwrap();
-        self.ws_stream.send(Message::Text(generate_request_str)).await?;
+        let step = with_timeout(timeout, kind, next_response(&mut self.ws_stream)).await.and_then(|step| step);
+        match &step {
+            Ok(response) => {
+                // Every step generated something on the server, even when its outputs decode to "".
+                let tokens = self.step_tokens.unwrap_or_else(|| self.token_counter().count(&response.outputs));
+                self.used_length += tokens.max(1);
+            }
+            Err(_) => self.failed = true,
+        }
+        match &step {
+            Ok(response) if response.stop => self.pending = false,
+            Err(PetalsError::ApiError { .. }) => self.pending = false,
+            _ => {}
+        }
+        step
+    }
+
+    // False once a generation was abandoned mid-way or anything went wrong on the session.
+    pub(crate) fn is_reusable(&self) -> bool {
+        !self.pending && !self.failed
+    }
+
+    // Applies the per-request settings of a config to an open session, e.g. when a pool hands it
+    // to a caller with other timeouts or token counter. Tokens already used stay as counted.
+    pub(crate) fn reconfigure(&mut self, config: &SessionConfig) {
+        self.first_token_timeout = config.first_token_timeout;
+        self.inter_token_timeout = config.inter_token_timeout;
+        self.token_counter = config.token_counter.clone();
+    }
+
+    async fn send_generate(&mut self, request: &GenerateRequest) -> Result<(), PetalsError> {
+        // A previous generation was cancelled or dropped before the server finished it.
+        while self.pending {
+            match self.next_step(false).await {
+                Ok(_) | Err(PetalsError::ApiError { .. }) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        // Fail before the server does: the inputs plus at least one new token must fit.
+        let inputs = request.inputs.as_deref().map_or(0, |inputs| self.token_counter().count(inputs));
+        let requested = inputs.saturating_add(request.max_new_tokens.unwrap_or(1));
+        if requested > self.remaining_length() {
+            return Err(PetalsError::BudgetExceeded {
+                requested,
+                remaining: self.remaining_length(),
+            });
+        }
+        self.send_message(&ClientMessage::Generate(request.clone())).await?;
+        self.pending = true;
+        self.used_length += inputs;
+        self.step_tokens = request.max_new_tokens;
         Ok(())
     }
+
+    async fn send_message(&mut self, message: &ClientMessage) -> Result<(), PetalsError> {
+        let result = self.ws_stream.send(Message::Text(message.encode()?.into())).await;
+        self.failed |= result.is_err();
+        Ok(result?)
+    }
+}
+
+async fn next_text<S: Transport>(ws_stream: &mut S) -> Result<String, PetalsError> {
+    loop {
+        let message = ws_stream.next().await.ok_or(PetalsError::ConnectionClosed)??;
+        return match message {
+            Message::Text(text) => Ok(text.to_string()),
+            Message::Close(_) => Err(PetalsError::ConnectionClosed),
+            Message::Ping(_) | Message::Pong(_) => continue,
+            message => Err(PetalsError::UnexpectedMessage(message)),
+        };
+    }
 }
 
-#[derive(Debug)]
+async fn next_response<S: Transport>(ws_stream: &mut S) -> Result<Response, PetalsError> {
+    let message = next_text(ws_stream).await?;
+    match ServerMessage::decode(&message)? {
+        ServerMessage::Step(response) => Ok(response),
+        ServerMessage::Error { traceback } => Err(PetalsError::ApiError { traceback }),
+        _ => Err(PetalsError::MissingField("outputs")),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct GenerateParams {
     model: Option<Model>,
     inputs: Option<String>,
@@ -129,10 +371,60 @@ pub struct GenerateParams {
     max_length: Option<u32>,
     max_new_tokens: Option<u32>,
     stop_sequence: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    stop_sequences: Vec<String>,
+}
+
+impl GenerateParams {
+    pub fn model(&self) -> Option<&Model> {
+        self.model.as_ref()
+    }
+
+    pub fn inputs(&self) -> Option<&str> {
+        self.inputs.as_deref()
+    }
+
+    pub fn do_sample(&self) -> Option<bool> {
+        self.do_sample
+    }
+
+    pub fn temperature(&self) -> Option<f32> {
+        self.temperature
+    }
+
+    pub fn top_k(&self) -> Option<u32> {
+        self.top_k
+    }
+
+    pub fn top_p(&self) -> Option<f32> {
+        self.top_p
+    }
+
+    pub fn max_length(&self) -> Option<u32> {
+        self.max_length
+    }
+
+    pub fn max_new_tokens(&self) -> Option<u32> {
+        self.max_new_tokens
+    }
+
+    pub fn stop_sequence(&self) -> Option<&str> {
+        self.stop_sequence.as_deref()
+    }
+
+    pub fn stop_sequences(&self) -> &[String] {
+        &self.stop_sequences
+    }
 }
 
 pub struct GenerateParamsBuilder(GenerateParams);
 
+impl Default for GenerateParamsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl GenerateParamsBuilder {
     pub fn new() -> Self {
         Self(GenerateParams {
@@ -145,6 +437,7 @@ impl GenerateParamsBuilder {
             max_length: None,
             max_new_tokens: None,
             stop_sequence: None,
+            stop_sequences: Vec::new(),
         })
     }
 
@@ -193,11 +486,45 @@ impl GenerateParamsBuilder {
         self
     }
 
-    pub fn build(self) -> Option<GenerateParams> {
-        if self.0.max_length.is_none() && self.0.max_new_tokens.is_none() {
-            return None;
+    pub fn stop_sequences(mut self, stop_sequences: Vec<String>) -> Self {
+        self.0.stop_sequence = stop_sequences.first().cloned();
+        self.0.stop_sequences = stop_sequences;
+        self
+    }
+
+    pub fn build(self) -> Result<GenerateParams, ParamsError> {
+        let params = self.0;
+        if params.max_length.is_none() && params.max_new_tokens.is_none() {
+            return Err(ParamsError::MissingLength);
+        }
+        if let Some(temperature) = params.temperature {
+            if params.do_sample == Some(true) && (temperature.is_nan() || temperature <= 0.0) {
+                return Err(ParamsError::InvalidTemperature(temperature));
+            }
+        }
+        if let Some(top_p) = params.top_p {
+            if !(top_p > 0.0 && top_p <= 1.0) {
+                return Err(ParamsError::InvalidTopP(top_p));
+            }
+        }
+        if params.top_k == Some(0) {
+            return Err(ParamsError::InvalidTopK);
+        }
+        if params.stop_sequence.as_deref() == Some("") || params.stop_sequences.iter().any(String::is_empty) {
+            return Err(ParamsError::EmptyStopSequence);
+        }
+
+        Ok(params)
+    }
+
+    pub fn build_for_session(self, session_max_length: u32) -> Result<GenerateParams, ParamsError> {
+        let params = self.build()?;
+        if let Some(max_length) = params.max_length {
+            if max_length > session_max_length {
+                return Err(ParamsError::MaxLengthExceeded { max_length, session_max_length });
+            }
         }
 
-        Some(self.0)
+        Ok(params)
     }
 }
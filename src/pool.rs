@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+use crate::{InferenceSession, Model, PetalsError, SessionConfig};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct PoolKey {
+    url: String,
+    model: Option<Model>,
+    max_length: u32,
+}
+
+struct Idle {
+    session: InferenceSession,
+    returned_at: Instant,
+}
+
+struct PoolState {
+    idle: Mutex<HashMap<PoolKey, Vec<Idle>>>,
+    idle_ttl: Duration,
+    share_context: bool,
+    min_remaining_length: Option<u32>,
+}
+
+impl PoolState {
+    fn accepts(&self, session: &InferenceSession) -> bool {
+        let min_remaining_length = self.min_remaining_length.unwrap_or(session.max_length() / 4).max(1);
+        session.is_reusable()
+            && (self.share_context || session.used_length() == 0)
+            && session.remaining_length() >= min_remaining_length
+    }
+}
+
+/// A Petals session keeps the attention cache of every generation that ran on it, so by
+/// default only sessions that were never used go back to the pool: prompts never see each
+/// other's context. `SessionPoolBuilder::share_context` opts into reusing used sessions.
+#[derive(Clone)]
+pub struct SessionPool {
+    state: Arc<PoolState>,
+    permits: Arc<Semaphore>,
+}
+
+impl SessionPool {
+    pub fn builder() -> SessionPoolBuilder {
+        SessionPoolBuilder::new()
+    }
+
+    /// Waits until fewer than `max_sessions` sessions are checked out, then hands out an idle
+    /// session for the same URL, model and `max_length`, or opens a new one. An idle session
+    /// takes on the first-token and inter-token timeouts and the token counter of `config`.
+    pub async fn get(&self, url: &str, config: &SessionConfig) -> Result<PooledSession, PetalsError> {
+        let permit = self.permits.clone().acquire_owned().await.expect("pool semaphore is never closed");
+        let key = PoolKey {
+            url: url.to_owned(),
+            model: config.model.clone(),
+            max_length: config.max_length,
+        };
+        while let Some(mut idle) = self.take_idle(&key) {
+            if idle.returned_at.elapsed() < self.state.idle_ttl
+                && self.state.accepts(&idle.session)
+                && idle.session.ping().await.is_ok()
+            {
+                idle.session.reconfigure(config);
+                return Ok(self.guard(key, idle.session, permit));
+            }
+        }
+        let session = InferenceSession::open_with(url, config).await?;
+        Ok(self.guard(key, session, permit))
+    }
+
+    pub fn idle_sessions(&self) -> usize {
+        self.state.idle.lock().unwrap().values().map(Vec::len).sum()
+    }
+
+    /// Drops idle sessions that have outlived the TTL.
+    pub fn evict_stale(&self) {
+        let idle_ttl = self.state.idle_ttl;
+        let mut idle = self.state.idle.lock().unwrap();
+        idle.retain(|_, sessions| {
+            sessions.retain(|idle| idle.returned_at.elapsed() < idle_ttl);
+            !sessions.is_empty()
+        });
+    }
+
+    fn take_idle(&self, key: &PoolKey) -> Option<Idle> {
+        // Most recently returned first: it is the least likely to have been closed by the server.
+        self.state.idle.lock().unwrap().get_mut(key)?.pop()
+    }
+
+    fn guard(&self, key: PoolKey, session: InferenceSession, permit: OwnedSemaphorePermit) -> PooledSession {
+        PooledSession {
+            session: Some(session),
+            key,
+            state: self.state.clone(),
+            _permit: permit,
+        }
+    }
+}
+
+impl Default for SessionPool {
+    fn default() -> Self {
+        SessionPoolBuilder::new().build()
+    }
+}
+
+/// Returns the session to its pool when dropped, unless the pool would not hand it out again:
+/// a generation was left unfinished, an error occurred, or too little of `max_length` is left.
+pub struct PooledSession {
+    session: Option<InferenceSession>,
+    key: PoolKey,
+    state: Arc<PoolState>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl PooledSession {
+    pub fn discard(mut self) {
+        self.session = None;
+    }
+}
+
+impl Deref for PooledSession {
+    type Target = InferenceSession;
+
+    fn deref(&self) -> &InferenceSession {
+        self.session.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledSession {
+    fn deref_mut(&mut self) -> &mut InferenceSession {
+        self.session.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledSession {
+    fn drop(&mut self) {
+        if let Some(session) = self.session.take().filter(|session| self.state.accepts(session)) {
+            let idle = Idle {
+                session,
+                returned_at: Instant::now(),
+            };
+            let mut sessions = self.state.idle.lock().unwrap();
+            sessions.entry(self.key.clone()).or_default().push(idle);
+        }
+    }
+}
+
+pub struct SessionPoolBuilder {
+    max_sessions: usize,
+    idle_ttl: Duration,
+    share_context: bool,
+    min_remaining_length: Option<u32>,
+}
+
+impl Default for SessionPoolBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SessionPoolBuilder {
+    pub fn new() -> Self {
+        Self {
+            max_sessions: 8,
+            idle_ttl: Duration::from_secs(60),
+            share_context: false,
+            min_remaining_length: None,
+        }
+    }
+
+    /// Upper bound on sessions checked out at the same time, across all keys.
+    pub fn max_sessions(mut self, max_sessions: usize) -> Self {
+        self.max_sessions = max_sessions.max(1);
+        self
+    }
+
+    pub fn idle_ttl(mut self, idle_ttl: Duration) -> Self {
+        self.idle_ttl = idle_ttl;
+        self
+    }
+
+    /// Reuse sessions that already ran generations. Later prompts are appended to the earlier
+    /// ones server-side, so only enable this when callers may see each other's context.
+    pub fn share_context(mut self, share_context: bool) -> Self {
+        self.share_context = share_context;
+        self
+    }
+
+    /// Sessions with fewer tokens left are retired instead of reused [default: max_length / 4].
+    pub fn min_remaining_length(mut self, min_remaining_length: u32) -> Self {
+        self.min_remaining_length = Some(min_remaining_length);
+        self
+    }
+
+    pub fn build(self) -> SessionPool {
+        SessionPool {
+            state: Arc::new(PoolState {
+                idle: Mutex::new(HashMap::new()),
+                idle_ttl: self.idle_ttl,
+                share_context: self.share_context,
+                min_remaining_length: self.min_remaining_length,
+            }),
+            permits: Arc::new(Semaphore::new(self.max_sessions)),
+        }
+    }
+}
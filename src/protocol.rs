@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{GenerateParams, Model, PetalsError};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OpenSessionRequest {
+    #[serde(rename = "type")]
+    pub request_type: RequestType,
+    pub max_length: u32,
+    pub model: Option<Model>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GenerateRequest {
+    #[serde(rename = "type")]
+    pub request_type: RequestType,
+    pub model: Option<Model>,
+    pub max_length: Option<u32>,
+    pub inputs: Option<String>,
+    pub stop_sequence: Option<String>,
+    pub do_sample: Option<bool>,
+    pub temperature: Option<f32>,
+    pub top_k: Option<u32>,
+    pub top_p: Option<f32>,
+    pub max_new_tokens: Option<u32>,
+}
+
+impl From<GenerateParams> for GenerateRequest {
+    fn from(params: GenerateParams) -> Self {
+        Self {
+            request_type: RequestType::Generate,
+            model: params.model,
+            max_length: params.max_length,
+            inputs: params.inputs,
+            stop_sequence: params.stop_sequence,
+            do_sample: params.do_sample,
+            temperature: params.temperature,
+            top_k: params.top_k,
+            top_p: params.top_p,
+            max_new_tokens: params.max_new_tokens,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RequestType {
+    #[serde(rename = "generate")]
+    Generate,
+    #[serde(rename = "open_inference_session")]
+    OpenInferenceSession,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub ok: bool,
+    pub outputs: String,
+    pub stop: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ClientMessage {
+    OpenInferenceSession(OpenSessionRequest),
+    Generate(GenerateRequest),
+}
+
+impl ClientMessage {
+    /// The variant decides the wire `type`; the request's `request_type` field is overwritten.
+    pub fn encode(&self) -> Result<String, PetalsError> {
+        let text = match self {
+            Self::OpenInferenceSession(request) => serde_json::to_string(&OpenSessionRequest {
+                request_type: RequestType::OpenInferenceSession,
+                ..request.clone()
+            })?,
+            Self::Generate(request) => serde_json::to_string(&GenerateRequest {
+                request_type: RequestType::Generate,
+                ..request.clone()
+            })?,
+        };
+        Ok(text)
+    }
+
+    pub fn decode(text: &str) -> Result<Self, PetalsError> {
+        let value: Value = serde_json::from_str(text)?;
+        let request_type = value.get("type").ok_or(PetalsError::MissingField("type"))?;
+        match RequestType::deserialize(request_type)? {
+            RequestType::OpenInferenceSession => Ok(Self::OpenInferenceSession(serde_json::from_value(value)?)),
+            RequestType::Generate => Ok(Self::Generate(serde_json::from_value(value)?)),
+        }
+    }
+}
+
+/// Server frames are not tagged: every frame carries `ok`, failures add a `traceback` and
+/// generation steps add `outputs` and `stop`. Anything else with `ok: true` acknowledges the
+/// `open_inference_session` request.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ServerMessage {
+    OpenAck,
+    Step(Response),
+    Error { traceback: String },
+}
+
+#[derive(Deserialize)]
+struct RawServerMessage {
+    ok: Option<bool>,
+    outputs: Option<String>,
+    stop: Option<bool>,
+    traceback: Option<String>,
+}
+
+impl ServerMessage {
+    pub fn encode(&self) -> Result<String, PetalsError> {
+        let text = match self {
+            Self::OpenAck => json!({ "ok": true }).to_string(),
+            Self::Step(response) => serde_json::to_string(response)?,
+            Self::Error { traceback } => json!({ "ok": false, "traceback": traceback }).to_string(),
+        };
+        Ok(text)
+    }
+
+    pub fn decode(text: &str) -> Result<Self, PetalsError> {
+        let raw: RawServerMessage = serde_json::from_str(text)?;
+        match (raw.ok.ok_or(PetalsError::MissingField("ok"))?, raw.outputs) {
+            (false, _) => Ok(Self::Error {
+                traceback: raw.traceback.unwrap_or_default(),
+            }),
+            (true, Some(outputs)) => Ok(Self::Step(Response {
+                ok: true,
+                outputs,
+                stop: raw.stop.ok_or(PetalsError::MissingField("stop"))?,
+            })),
+            (true, None) => Ok(Self::OpenAck),
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use futures_util::{stream, Stream};
+
+use crate::stop::enforce_stop_sequences;
+use crate::{
+    GenerateParams, GenerateRequest, InferenceSession, Model, PetalsError, Response, SessionConfig, SessionConfigBuilder,
+};
+
+/// Keeps a copy of everything that went through the session (inputs and generated outputs).
+/// After a reconnect the whole history is sent as the inputs of the next request, which
+/// rebuilds the server-side attention cache and continues generation where it stopped.
+///
+/// The history mirrors the server's cache rather than what the caller was handed: text
+/// generated past a stop sequence is kept, and the frames of a generation that was dropped or
+/// ended by a stop sequence are read and appended before the next request is sent.
+pub struct ResilientSession {
+    url: String,
+    config: SessionConfig,
+    max_reconnects: u32,
+    session: InferenceSession,
+    history: String,
+}
+
+impl ResilientSession {
+    pub async fn open(url: &str, max_length: u32, model: Option<Model>) -> Result<Self, PetalsError> {
+        let mut config = SessionConfigBuilder::new(max_length);
+        if let Some(model) = model {
+            config = config.model(model);
+        }
+        Self::open_with(url, config.build()).await
+    }
+
+    /// Reconnects use the same config, so they get its retry policy and timeouts.
+    pub async fn open_with(url: &str, config: SessionConfig) -> Result<Self, PetalsError> {
+        let session = InferenceSession::open_with(url, &config).await?;
+        Ok(Self {
+            url: url.to_owned(),
+            config,
+            max_reconnects: 3,
+            session,
+            history: String::new(),
+        })
+    }
+
+    pub fn max_reconnects(mut self, max_reconnects: u32) -> Self {
+        self.max_reconnects = max_reconnects;
+        self
+    }
+
+    pub fn history(&self) -> &str {
+        &self.history
+    }
+
+    pub async fn generate(
+        &mut self,
+        params: GenerateParams,
+    ) -> Result<impl Stream<Item = Result<Response, PetalsError>> + '_, PetalsError> {
+        let stop_sequences = params.stop_sequences.clone();
+        let request = GenerateRequest::from(params);
+        let inputs = request.inputs.clone().unwrap_or_default();
+        let mut reconnects = 0;
+        let sent = match self.drain().await {
+            Ok(()) => self.session.send_generate(&request).await,
+            Err(e) => Err(e),
+        };
+        match sent {
+            Err(e) if e.is_disconnect() && self.max_reconnects > 0 => {
+                self.resume(&request, &inputs, &mut reconnects).await?
+            }
+            result => result?,
+        }
+        self.history.push_str(&inputs);
+        let steps = stream::unfold(Some((self, request, reconnects, true)), |state| async move {
+            let (this, request, mut reconnects, mut first) = state?;
+            loop {
+                match this.session.next_step(first).await {
+                    Ok(response) => {
+                        this.history.push_str(&response.outputs);
+                        let state = (!response.stop).then_some((this, request, reconnects, false));
+                        return Some((Ok(response), state));
+                    }
+                    Err(e) if e.is_disconnect() && reconnects < this.max_reconnects => {
+                        if let Err(e) = this.resume(&request, "", &mut reconnects).await {
+                            return Some((Err(e), None));
+                        }
+                        first = true;
+                    }
+                    Err(e) => return Some((Err(e), None)),
+                }
+            }
+        });
+        Ok(enforce_stop_sequences(steps, stop_sequences))
+    }
+
+    // Reads what is left of an unfinished generation into the history, so it matches the cache.
+    async fn drain(&mut self) -> Result<(), PetalsError> {
+        while self.session.pending {
+            match self.session.next_step(false).await {
+                Ok(response) => self.history.push_str(&response.outputs),
+                Err(PetalsError::ApiError { .. }) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    // `unsent` holds inputs of the current request that the server has not seen yet; the caller
+    // adds them to the history once the request is on its way.
+    async fn resume(&mut self, request: &GenerateRequest, unsent: &str, reconnects: &mut u32) -> Result<(), PetalsError> {
+        let mut request = request.clone();
+        request.inputs = Some(format!("{}{unsent}", self.history));
+        loop {
+            if *reconnects > 0 {
+                tokio::time::sleep(self.config.retry_policy.backoff(*reconnects - 1)).await;
+            }
+            *reconnects += 1;
+            let result = match InferenceSession::open_with(self.url.as_str(), &self.config).await {
+                Ok(session) => {
+                    self.session = session;
+                    self.session.send_generate(&request).await
+                }
+                Err(e) => Err(e),
+            };
+            match result {
+                Err(e) if e.is_disconnect() && *reconnects < self.max_reconnects => continue,
+                result => return result,
+            }
+        }
+    }
+}
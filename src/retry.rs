@@ -0,0 +1,168 @@
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::time::Instant;
+
+use crate::{PetalsError, TimeoutKind};
+
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: bool,
+    deadline: Option<Duration>,
+    retryable: fn(&PetalsError) -> bool,
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        RetryPolicyBuilder::new().max_attempts(1).build()
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    pub fn is_retryable(&self, e: &PetalsError) -> bool {
+        (self.retryable)(e)
+    }
+
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exponent = i32::try_from(retry).unwrap_or(i32::MAX);
+        let secs = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        // `secs` overflows to infinity after enough retries, or is NaN for a zero initial backoff.
+        let backoff = match Duration::try_from_secs_f64(secs) {
+            Ok(backoff) => backoff.min(self.max_backoff),
+            Err(_) if self.initial_backoff.is_zero() => Duration::ZERO,
+            Err(_) => self.max_backoff,
+        };
+        if self.jitter {
+            // Equal jitter: keep half of the delay and randomize the other half.
+            backoff / 2 + backoff.mul_f64(random_fraction() / 2.0)
+        } else {
+            backoff
+        }
+    }
+
+    pub(crate) async fn retry<T, F, Fut>(&self, mut attempt: F) -> Result<T, PetalsError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, PetalsError>>,
+    {
+        let started = Instant::now();
+        let mut retry = 0;
+        loop {
+            let result = match self.deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_sub(started.elapsed());
+                    tokio::time::timeout(remaining, attempt())
+                        .await
+                        .unwrap_or(Err(PetalsError::Timeout(TimeoutKind::Deadline)))
+                }
+                None => attempt().await,
+            };
+            let e = match result {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+            retry += 1;
+            if retry >= self.max_attempts || !self.is_retryable(&e) {
+                return Err(e);
+            }
+            let backoff = self.backoff(retry - 1);
+            if let Some(deadline) = self.deadline {
+                if started.elapsed() + backoff >= deadline {
+                    return Err(e);
+                }
+            }
+            tokio::time::sleep(backoff).await;
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicyBuilder::new().build()
+    }
+}
+
+fn default_retryable(e: &PetalsError) -> bool {
+    // Overloaded public swarms reject sessions with a traceback or stall, so those are worth retrying too.
+    e.is_disconnect()
+        || matches!(
+            e,
+            PetalsError::ApiError { .. } | PetalsError::Timeout(TimeoutKind::Connect | TimeoutKind::Handshake)
+        )
+}
+
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos());
+    hasher.finish() as f64 / u64::MAX as f64
+}
+
+pub struct RetryPolicyBuilder(RetryPolicy);
+
+impl Default for RetryPolicyBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RetryPolicyBuilder {
+    pub fn new() -> Self {
+        Self(RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: true,
+            deadline: None,
+            retryable: default_retryable,
+        })
+    }
+
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.0.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.0.initial_backoff = initial_backoff;
+        self
+    }
+
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.0.max_backoff = max_backoff;
+        self
+    }
+
+    /// Values below 1.0 are raised to 1.0; NaN and infinity are replaced by 1.0.
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.0.multiplier = if multiplier.is_finite() { multiplier.max(1.0) } else { 1.0 };
+        self
+    }
+
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.0.jitter = jitter;
+        self
+    }
+
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.0.deadline = Some(deadline);
+        self
+    }
+
+    pub fn retry_if(mut self, retryable: fn(&PetalsError) -> bool) -> Self {
+        self.0.retryable = retryable;
+        self
+    }
+
+    pub fn build(self) -> RetryPolicy {
+        self.0
+    }
+}
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+
+use crate::GenerateParamsBuilder;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SamplingConfig {
+    pub do_sample: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+}
+
+impl SamplingConfig {
+    pub fn greedy() -> Self {
+        Self {
+            do_sample: false,
+            temperature: None,
+            top_k: None,
+            top_p: None,
+        }
+    }
+
+    pub fn balanced() -> Self {
+        Self {
+            do_sample: true,
+            temperature: Some(0.7),
+            top_k: None,
+            top_p: Some(0.9),
+        }
+    }
+
+    pub fn creative() -> Self {
+        Self {
+            do_sample: true,
+            temperature: Some(1.0),
+            top_k: None,
+            top_p: Some(0.95),
+        }
+    }
+
+    pub fn precise() -> Self {
+        Self {
+            do_sample: true,
+            temperature: Some(0.2),
+            top_k: Some(40),
+            top_p: Some(0.75),
+        }
+    }
+}
+
+impl GenerateParamsBuilder {
+    pub fn sampling(mut self, config: SamplingConfig) -> Self {
+        self.0.do_sample = Some(config.do_sample);
+        self.0.temperature = config.temperature;
+        self.0.top_k = config.top_k;
+        self.0.top_p = config.top_p;
+        self
+    }
+
+    pub fn sampling_config(&self) -> SamplingConfig {
+        SamplingConfig {
+            do_sample: self.0.do_sample.unwrap_or(false),
+            temperature: self.0.temperature,
+            top_k: self.0.top_k,
+            top_p: self.0.top_p,
+        }
+    }
+
+    pub fn greedy(self) -> Self {
+        self.sampling(SamplingConfig::greedy())
+    }
+
+    pub fn balanced(self) -> Self {
+        self.sampling(SamplingConfig::balanced())
+    }
+
+    pub fn creative(self) -> Self {
+        self.sampling(SamplingConfig::creative())
+    }
+
+    pub fn precise(self) -> Self {
+        self.sampling(SamplingConfig::precise())
+    }
+}
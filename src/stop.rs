@@ -0,0 +1,62 @@
+use futures_util::{stream, Stream, StreamExt};
+
+use crate::{PetalsError, Response};
+
+// The server only understands a single `stop_sequence`, so every sequence passed to
+// `GenerateParamsBuilder::stop_sequences` is also matched here. Text that could be the start
+// of a stop sequence is held back until the next step shows whether it matches.
+struct StopMatcher {
+    stop_sequences: Vec<String>,
+    buffer: String,
+}
+
+impl StopMatcher {
+    fn push(&mut self, chunk: &str) -> (String, bool) {
+        self.buffer.push_str(chunk);
+        let matched = self.stop_sequences.iter().filter_map(|stop| self.buffer.find(stop.as_str())).min();
+        if let Some(end) = matched {
+            self.buffer.truncate(end);
+            return (std::mem::take(&mut self.buffer), true);
+        }
+        let held = self
+            .buffer
+            .char_indices()
+            .map(|(i, _)| i)
+            .find(|&i| self.stop_sequences.iter().any(|stop| stop.starts_with(&self.buffer[i..])))
+            .unwrap_or(self.buffer.len());
+        (self.buffer.drain(..held).collect(), false)
+    }
+
+    fn finish(&mut self) -> String {
+        std::mem::take(&mut self.buffer)
+    }
+}
+
+pub(crate) fn enforce_stop_sequences<'a, S>(
+    steps: S,
+    stop_sequences: Vec<String>,
+) -> impl Stream<Item = Result<Response, PetalsError>> + Send + 'a
+where
+    S: Stream<Item = Result<Response, PetalsError>> + Send + 'a,
+{
+    let matcher = StopMatcher {
+        stop_sequences,
+        buffer: String::new(),
+    };
+    stream::unfold(Some((Box::pin(steps), matcher)), |state| async move {
+        let (mut steps, mut matcher) = state?;
+        let mut response = match steps.next().await? {
+            Ok(response) => response,
+            Err(e) => return Some((Err(e), None)),
+        };
+        let (outputs, matched) = matcher.push(&response.outputs);
+        response.outputs = outputs;
+        if matched {
+            response.stop = true;
+        } else if response.stop {
+            response.outputs.push_str(&matcher.finish());
+        }
+        let state = (!response.stop).then_some((steps, matcher));
+        Some((Ok(response), state))
+    })
+}
@@ -0,0 +1,162 @@
+use crate::{GenerateParamsBuilder, Model};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChatMessage {
+    pub role: Role,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn system(content: &str) -> Self {
+        Self { role: Role::System, content: content.to_owned() }
+    }
+
+    pub fn user(content: &str) -> Self {
+        Self { role: Role::User, content: content.to_owned() }
+    }
+
+    pub fn assistant(content: &str) -> Self {
+        Self { role: Role::Assistant, content: content.to_owned() }
+    }
+}
+
+pub trait ChatTemplate {
+    fn format(&self, messages: &[ChatMessage]) -> String;
+
+    fn stop_sequence(&self) -> &str;
+
+    fn apply(&self, messages: &[ChatMessage], builder: GenerateParamsBuilder) -> GenerateParamsBuilder {
+        builder
+            .inputs(self.format(messages))
+            .stop_sequence(self.stop_sequence().to_owned())
+    }
+}
+
+/// `[INST]` / `<<SYS>>` format used by meta-llama/Llama-2-*-chat-hf.
+pub struct Llama2Chat;
+
+impl ChatTemplate for Llama2Chat {
+    fn format(&self, messages: &[ChatMessage]) -> String {
+        let mut prompt = String::new();
+        let mut system = None;
+        for message in messages {
+            match message.role {
+                Role::System => system = Some(&message.content),
+                Role::User => {
+                    prompt.push_str("<s>[INST] ");
+                    if let Some(system) = system.take() {
+                        prompt.push_str(&format!("<<SYS>>\n{system}\n<</SYS>>\n\n"));
+                    }
+                    prompt.push_str(&format!("{} [/INST]", message.content));
+                }
+                Role::Assistant => prompt.push_str(&format!(" {} </s>", message.content)),
+            }
+        }
+        prompt
+    }
+
+    fn stop_sequence(&self) -> &str {
+        "</s>"
+    }
+}
+
+/// `### System:` / `### User:` / `### Assistant:` format used by stabilityai/StableBeluga2.
+pub struct StableBeluga;
+
+impl ChatTemplate for StableBeluga {
+    fn format(&self, messages: &[ChatMessage]) -> String {
+        let mut prompt = String::new();
+        for message in messages {
+            let header = match message.role {
+                Role::System => "### System:",
+                Role::User => "### User:",
+                Role::Assistant => "### Assistant:",
+            };
+            prompt.push_str(&format!("{header}\n{}\n\n", message.content));
+        }
+        if !matches!(messages.last(), Some(message) if message.role == Role::Assistant) {
+            prompt.push_str("### Assistant:\n");
+        }
+        prompt
+    }
+
+    fn stop_sequence(&self) -> &str {
+        "###"
+    }
+}
+
+/// `### Human:` / `### Assistant:` format used by timdettmers/guanaco-65b.
+pub struct Guanaco;
+
+impl ChatTemplate for Guanaco {
+    fn format(&self, messages: &[ChatMessage]) -> String {
+        let mut prompt = String::new();
+        for message in messages {
+            match message.role {
+                Role::System => prompt.push_str(&format!("{}\n", message.content)),
+                Role::User => prompt.push_str(&format!("### Human: {}\n", message.content)),
+                Role::Assistant => prompt.push_str(&format!("### Assistant: {}\n", message.content)),
+            }
+        }
+        if !matches!(messages.last(), Some(message) if message.role == Role::Assistant) {
+            prompt.push_str("### Assistant:");
+        }
+        prompt
+    }
+
+    fn stop_sequence(&self) -> &str {
+        "### Human:"
+    }
+}
+
+/// Plain transcript for base models without a chat format (llama-65b-hf, bloomz, custom models).
+pub struct PlainTranscript;
+
+impl ChatTemplate for PlainTranscript {
+    fn format(&self, messages: &[ChatMessage]) -> String {
+        let mut prompt = String::new();
+        for message in messages {
+            match message.role {
+                Role::System => prompt.push_str(&format!("{}\n\n", message.content)),
+                Role::User => prompt.push_str(&format!("Human: {}\n", message.content)),
+                Role::Assistant => prompt.push_str(&format!("Assistant: {}\n", message.content)),
+            }
+        }
+        if !matches!(messages.last(), Some(message) if message.role == Role::Assistant) {
+            prompt.push_str("Assistant:");
+        }
+        prompt
+    }
+
+    fn stop_sequence(&self) -> &str {
+        "\nHuman:"
+    }
+}
+
+impl Model {
+    pub fn chat_template(&self) -> &'static dyn ChatTemplate {
+        match self {
+            Self::Llama2_70bChatHf => &Llama2Chat,
+            Self::StableBeluga2 => &StableBeluga,
+            Self::Guanaco65b => &Guanaco,
+            Self::Llama65bHf | Self::Bloomz | Self::Custom(_) => &PlainTranscript,
+        }
+    }
+}
+
+impl ChatTemplate for Model {
+    fn format(&self, messages: &[ChatMessage]) -> String {
+        self.chat_template().format(messages)
+    }
+
+    fn stop_sequence(&self) -> &str {
+        self.chat_template().stop_sequence()
+    }
+}
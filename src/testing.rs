@@ -0,0 +1,182 @@
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::Value;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::accept_async;
+use tokio_tungstenite::tungstenite::protocol::Message;
+
+use crate::protocol::{ClientMessage, ServerMessage};
+use crate::Response;
+
+#[derive(Clone, Debug)]
+pub enum Reply {
+    Ok,
+    Step { outputs: String, stop: bool },
+    Error { traceback: String },
+    Raw(Message),
+    Delay(Duration),
+    Disconnect,
+}
+
+impl Reply {
+    pub fn step(outputs: &str) -> Self {
+        Self::Step { outputs: outputs.to_owned(), stop: false }
+    }
+
+    pub fn last_step(outputs: &str) -> Self {
+        Self::Step { outputs: outputs.to_owned(), stop: true }
+    }
+
+    pub fn error(traceback: &str) -> Self {
+        Self::Error { traceback: traceback.to_owned() }
+    }
+
+    fn into_message(self) -> Message {
+        let message = match self {
+            Self::Ok => ServerMessage::OpenAck,
+            Self::Step { outputs, stop } => ServerMessage::Step(Response { ok: true, outputs, stop }),
+            Self::Error { traceback } => ServerMessage::Error { traceback },
+            Self::Raw(message) => return message,
+            Self::Delay(_) | Self::Disconnect => unreachable!(),
+        };
+        Message::Text(message.encode().unwrap().into())
+    }
+}
+
+#[derive(Default)]
+struct Script {
+    open: VecDeque<Vec<Reply>>,
+    generate: VecDeque<Vec<Reply>>,
+}
+
+pub struct MockServerBuilder(Script);
+
+impl Default for MockServerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockServerBuilder {
+    pub fn new() -> Self {
+        Self(Script::default())
+    }
+
+    pub fn open(mut self, replies: Vec<Reply>) -> Self {
+        self.0.open.push_back(replies);
+        self
+    }
+
+    pub fn generate(mut self, replies: Vec<Reply>) -> Self {
+        self.0.generate.push_back(replies);
+        self
+    }
+
+    pub fn tokens(self, tokens: &[&str]) -> Self {
+        let mut replies: Vec<Reply> = tokens.iter().map(|token| Reply::step(token)).collect();
+        if let Some(Reply::Step { stop, .. }) = replies.last_mut() {
+            *stop = true;
+        }
+        self.generate(replies)
+    }
+
+    pub async fn start(self) -> MockServer {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let state = Arc::new(State {
+            script: Mutex::new(self.0),
+            requests: Mutex::new(Vec::new()),
+            connections: AtomicUsize::new(0),
+        });
+        let task = tokio::spawn({
+            let state = state.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    state.connections.fetch_add(1, Ordering::SeqCst);
+                    tokio::spawn(serve(stream, state.clone()));
+                }
+            }
+        });
+        MockServer { addr, state, task }
+    }
+}
+
+struct State {
+    script: Mutex<Script>,
+    requests: Mutex<Vec<Value>>,
+    connections: AtomicUsize,
+}
+
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<State>,
+    task: JoinHandle<()>,
+}
+
+impl MockServer {
+    pub fn builder() -> MockServerBuilder {
+        MockServerBuilder::new()
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn url(&self) -> String {
+        format!("ws://{}/api/v2/generate", self.addr)
+    }
+
+    pub fn requests(&self) -> Vec<Value> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    pub fn connections(&self) -> usize {
+        self.state.connections.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn serve(stream: TcpStream, state: Arc<State>) {
+    let Ok(mut ws_stream) = accept_async(stream).await else {
+        return;
+    };
+    while let Some(Ok(message)) = ws_stream.next().await {
+        let Message::Text(text) = message else {
+            continue;
+        };
+        let Ok(request) = serde_json::from_str::<Value>(&text) else {
+            continue;
+        };
+        let replies = {
+            let mut script = state.script.lock().unwrap();
+            match ClientMessage::decode(&text) {
+                Ok(ClientMessage::OpenInferenceSession(_)) => script.open.pop_front().unwrap_or_else(|| vec![Reply::Ok]),
+                Ok(ClientMessage::Generate(_)) => script.generate.pop_front().unwrap_or_else(|| vec![Reply::last_step("")]),
+                Err(e) => vec![Reply::error(&e.to_string())],
+            }
+        };
+        state.requests.lock().unwrap().push(request);
+        for reply in replies {
+            match reply {
+                Reply::Delay(duration) => tokio::time::sleep(duration).await,
+                Reply::Disconnect => return,
+                reply => {
+                    if ws_stream.send(reply.into_message()).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::io;
+#[cfg(feature = "rustls")]
+use std::sync::Arc;
+
+use tokio::net::TcpStream;
+
+use crate::{Io, TlsConnect, TlsConnector};
+
+#[cfg(feature = "native-tls")]
+pub use tokio_native_tls::native_tls;
+#[cfg(feature = "rustls")]
+pub use tokio_rustls::rustls;
+
+/// [`TlsConnector`] backed by rustls, trusting the Mozilla roots from `webpki-roots` by default.
+#[cfg(feature = "rustls")]
+#[derive(Clone)]
+pub struct RustlsConnector(tokio_rustls::TlsConnector);
+
+#[cfg(feature = "rustls")]
+impl RustlsConnector {
+    pub fn new() -> Self {
+        let mut roots = rustls::RootCertStore::empty();
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        let config = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .expect("ring supports the default protocol versions")
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Self::from_config(Arc::new(config))
+    }
+
+    /// Uses a caller-built config, e.g. with private CA roots or a client certificate.
+    pub fn from_config(config: Arc<rustls::ClientConfig>) -> Self {
+        Self(tokio_rustls::TlsConnector::from(config))
+    }
+}
+
+#[cfg(feature = "rustls")]
+impl Default for RustlsConnector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "rustls")]
+impl TlsConnector for RustlsConnector {
+    fn connect(&self, server_name: String, stream: TcpStream) -> TlsConnect<'_> {
+        Box::pin(async move {
+            let server_name = rustls::pki_types::ServerName::try_from(server_name)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            let stream = self.0.connect(server_name, stream).await?;
+            Ok(Box::new(stream) as Box<dyn Io>)
+        })
+    }
+}
+
+/// [`TlsConnector`] backed by the platform's TLS library (OpenSSL, Secure Transport or SChannel).
+#[cfg(feature = "native-tls")]
+#[derive(Clone)]
+pub struct NativeTlsConnector(tokio_native_tls::TlsConnector);
+
+#[cfg(feature = "native-tls")]
+impl NativeTlsConnector {
+    pub fn new() -> io::Result<Self> {
+        let connector = native_tls::TlsConnector::new().map_err(io::Error::other)?;
+        Ok(Self::from(connector))
+    }
+}
+
+#[cfg(feature = "native-tls")]
+impl From<native_tls::TlsConnector> for NativeTlsConnector {
+    fn from(connector: native_tls::TlsConnector) -> Self {
+        Self(tokio_native_tls::TlsConnector::from(connector))
+    }
+}
+
+#[cfg(feature = "native-tls")]
+impl TlsConnector for NativeTlsConnector {
+    fn connect(&self, server_name: String, stream: TcpStream) -> TlsConnect<'_> {
+        Box::pin(async move {
+            let stream = self.0.connect(&server_name, stream).await.map_err(io::Error::other)?;
+            Ok(Box::new(stream) as Box<dyn Io>)
+        })
+    }
+}
@@ -0,0 +1,34 @@
+use std::fmt;
+use std::sync::Arc;
+
+/// Petals does not report token counts, so sessions measure the inputs they send, and the
+/// outputs of requests without `max_new_tokens`, with a counter. Plug in the model's real
+/// tokenizer for exact accounting.
+pub trait TokenCounter: Send + Sync {
+    fn count(&self, text: &str) -> u32;
+}
+
+/// Roughly four characters per token, which holds up for English text on Llama-family tokenizers.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CharEstimate;
+
+impl TokenCounter for CharEstimate {
+    fn count(&self, text: &str) -> u32 {
+        (text.chars().count() as u32).div_ceil(4)
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct SharedCounter(pub(crate) Arc<dyn TokenCounter>);
+
+impl Default for SharedCounter {
+    fn default() -> Self {
+        Self(Arc::new(CharEstimate))
+    }
+}
+
+impl fmt::Debug for SharedCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TokenCounter")
+    }
+}
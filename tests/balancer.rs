@@ -0,0 +1,85 @@
+mod common;
+
+use std::time::Duration;
+
+use common::config;
+
+use websocket_petals_api::testing::{MockServer, Reply};
+use websocket_petals_api::{ConnectionConfigBuilder, MultiEndpointClient, PetalsError, Routing};
+
+#[tokio::test]
+async fn round_robin_follows_weights() {
+    let heavy = MockServer::builder().start().await;
+    let light = MockServer::builder().start().await;
+    let client = MultiEndpointClient::builder().endpoint(&heavy.url(), 2).endpoint(&light.url(), 1).build();
+
+    for _ in 0..6 {
+        client.open(&config(512)).await.unwrap();
+    }
+    assert_eq!((heavy.connections(), light.connections()), (4, 2));
+}
+
+#[tokio::test]
+async fn fails_over_and_skips_unhealthy_endpoints() {
+    let busy = MockServer::builder().open(vec![Reply::error("busy")]).open(vec![Reply::error("busy")]).start().await;
+    let healthy = MockServer::builder().start().await;
+    let client = MultiEndpointClient::builder()
+        .endpoint(&busy.url(), 1)
+        .endpoint(&healthy.url(), 1)
+        .failure_threshold(2)
+        .cooldown(Duration::from_millis(100))
+        .build();
+
+    for _ in 0..6 {
+        client.open(&config(512)).await.unwrap();
+    }
+    assert_eq!(busy.connections(), 2);
+    assert_eq!(client.healthy_endpoints(), [healthy.url()]);
+
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(client.healthy_endpoints().len(), 2);
+    client.open(&config(512)).await.unwrap();
+    client.open(&config(512)).await.unwrap();
+    assert_eq!(busy.connections(), 3);
+}
+
+#[tokio::test]
+async fn least_latency_prefers_faster_endpoint() {
+    let slow = MockServer::builder().open(vec![Reply::Delay(Duration::from_millis(100)), Reply::Ok]).start().await;
+    let fast = MockServer::builder().start().await;
+    let client = MultiEndpointClient::builder()
+        .endpoint(&slow.url(), 1)
+        .endpoint(&fast.url(), 1)
+        .routing(Routing::LeastLatency)
+        .build();
+
+    for _ in 0..4 {
+        client.open(&config(512)).await.unwrap();
+    }
+    assert_eq!((slow.connections(), fast.connections()), (1, 3));
+}
+
+#[tokio::test]
+async fn returns_last_error_when_every_endpoint_fails() {
+    let busy = MockServer::builder().open(vec![Reply::error("busy")]).start().await;
+    let client = MultiEndpointClient::builder().endpoint(&busy.url(), 1).build();
+
+    let e = client.open(&config(512)).await.err().unwrap();
+    assert!(matches!(e, PetalsError::ApiError { traceback } if traceback == "busy"));
+}
+
+#[tokio::test]
+async fn opens_through_per_endpoint_connection_config() {
+    let server = MockServer::builder().start().await;
+    let connection = ConnectionConfigBuilder::new(&server.url()).bearer_token("secret").build();
+    let client = MultiEndpointClient::builder().connection(connection, 1).build();
+
+    client.open(&config(512)).await.unwrap();
+    assert_eq!(server.connections(), 1);
+}
+
+#[tokio::test]
+async fn fails_without_endpoints() {
+    let client = MultiEndpointClient::builder().build();
+    assert!(matches!(client.open(&config(512)).await, Err(PetalsError::NoEndpoints)));
+}
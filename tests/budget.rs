@@ -0,0 +1,69 @@
+mod common;
+
+use common::builder;
+use websocket_petals_api::testing::{MockServer, Reply};
+use websocket_petals_api::{GenerateParamsBuilder, InferenceSession, PetalsError, SessionConfigBuilder, TokenCounter};
+
+#[tokio::test]
+async fn counts_inputs_and_outputs() {
+    let server = MockServer::builder().tokens(&["Hi", " there"]).start().await;
+    let mut session = InferenceSession::open(server.url(), 64, None).await.unwrap();
+    assert_eq!(session.max_length(), 64);
+
+    // Inputs go through the token counter; each step counts as `max_new_tokens` tokens.
+    session.generate_text(builder("Hello!").max_new_tokens(2).build().unwrap()).await.unwrap();
+    assert_eq!(session.used_length(), 2 + 2 + 2);
+    assert_eq!(session.remaining_length(), 58);
+}
+
+#[tokio::test]
+async fn measures_outputs_without_max_new_tokens() {
+    // With only `max_length`, the server sends the whole completion in a single step.
+    let reply = "a sixty character completion that arrives in one single step";
+    let server = MockServer::builder().generate(vec![Reply::last_step(reply)]).start().await;
+    let mut session = InferenceSession::open(server.url(), 64, None).await.unwrap();
+
+    let params = GenerateParamsBuilder::new().inputs("Hello!".to_owned()).max_length(64).build().unwrap();
+    assert_eq!(session.generate_text(params).await.unwrap(), reply);
+    assert_eq!(session.used_length(), 2 + 15);
+}
+
+#[tokio::test]
+async fn counts_steps_with_empty_outputs() {
+    let server = MockServer::builder().tokens(&["", "", "a"]).start().await;
+    let mut session = InferenceSession::open(server.url(), 64, None).await.unwrap();
+
+    session.generate_text(builder("Hello!").max_new_tokens(2).build().unwrap()).await.unwrap();
+    assert_eq!(session.used_length(), 2 + 3 * 2);
+}
+
+#[tokio::test]
+async fn rejects_over_budget_generate_without_sending() {
+    let server = MockServer::builder().start().await;
+    let mut session = InferenceSession::open(server.url(), 8, None).await.unwrap();
+    match session.generate(builder("a prompt that is far too long").max_new_tokens(4).build().unwrap()).await {
+        Err(PetalsError::BudgetExceeded { requested: 12, remaining: 8 }) => {}
+        Err(e) => panic!("expected BudgetExceeded, got {e:?}"),
+        Ok(_) => panic!("expected BudgetExceeded"),
+    }
+    assert_eq!(server.requests().len(), 1);
+    assert_eq!(session.used_length(), 0);
+}
+
+struct Words;
+
+impl TokenCounter for Words {
+    fn count(&self, text: &str) -> u32 {
+        text.split_whitespace().count() as u32
+    }
+}
+
+#[tokio::test]
+async fn uses_configured_token_counter() {
+    let server = MockServer::builder().tokens(&["four five"]).start().await;
+    let config = SessionConfigBuilder::new(16).token_counter(Words).build();
+    let mut session = InferenceSession::open_with(server.url(), &config).await.unwrap();
+
+    session.generate_text(builder("one two three").max_new_tokens(2).build().unwrap()).await.unwrap();
+    assert_eq!(session.used_length(), 5);
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use std::time::Duration;
+
+use common::params;
+use futures_util::StreamExt;
+use websocket_petals_api::testing::{MockServer, Reply};
+use websocket_petals_api::{CancelHandle, InferenceSession};
+
+#[tokio::test]
+async fn cancelled_generation_leaves_session_usable() {
+    let server = MockServer::builder().tokens(&["a", "b", "c", "d"]).tokens(&["x", "y"]).start().await;
+    let mut session = InferenceSession::open(server.url(), 512, None).await.unwrap();
+
+    let mut steps = session.generate(params("")).await.unwrap();
+    assert_eq!(steps.next().await.unwrap().unwrap().outputs, "a");
+    steps.cancel_handle().cancel();
+    assert!(steps.next().await.is_none());
+    drop(steps);
+
+    assert_eq!(session.generate_text(params("")).await.unwrap(), "xy");
+}
+
+#[tokio::test]
+async fn cancel_before_first_step() {
+    let server = MockServer::builder().tokens(&["a", "b"]).start().await;
+    let mut session = InferenceSession::open(server.url(), 512, None).await.unwrap();
+
+    let cancel = CancelHandle::new();
+    cancel.cancel();
+    let steps: Vec<_> = session.generate_with_cancel(params(""), cancel).await.unwrap().collect().await;
+    assert!(steps.is_empty());
+    assert_eq!(server.requests().len(), 1);
+
+    assert_eq!(session.generate_text(params("")).await.unwrap(), "ab");
+}
+
+#[tokio::test]
+async fn dropped_generation_leaves_session_usable() {
+    let server = MockServer::builder().tokens(&["a", "b", "c"]).tokens(&["x"]).start().await;
+    let mut session = InferenceSession::open(server.url(), 512, None).await.unwrap();
+
+    let mut steps = session.generate(params("")).await.unwrap();
+    steps.next().await.unwrap().unwrap();
+    drop(steps);
+
+    assert_eq!(session.generate_text(params("")).await.unwrap(), "x");
+}
+
+#[tokio::test]
+async fn shared_handle_cancels_every_generation() {
+    let stall = || vec![Reply::Delay(Duration::from_secs(30)), Reply::last_step("late")];
+    let server = MockServer::builder().generate(stall()).generate(stall()).start().await;
+    let mut first = InferenceSession::open(server.url(), 512, None).await.unwrap();
+    let mut second = InferenceSession::open(server.url(), 512, None).await.unwrap();
+
+    let cancel = CancelHandle::new();
+    let first = first.generate_with_cancel(params(""), cancel.clone()).await.unwrap();
+    let second = second.generate_with_cancel(params(""), cancel.clone()).await.unwrap();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        cancel.cancel();
+    });
+    let both = futures_util::future::join(first.collect::<Vec<_>>(), second.collect::<Vec<_>>());
+    let (first, second) = tokio::time::timeout(Duration::from_secs(2), both).await.unwrap();
+    assert!(first.is_empty() && second.is_empty());
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use common::params;
+use futures_util::StreamExt;
+use websocket_petals_api::testing::MockServer;
+use websocket_petals_api::{ChatSession, PetalsError};
+
+#[tokio::test]
+async fn chat_sends_only_new_turns_and_tracks_length() {
+    let server = MockServer::builder().tokens(&["Hi", " there"]).tokens(&["Fine"]).start().await;
+    let mut chat = ChatSession::open(server.url(), 64, None).await.unwrap();
+
+    let replies: Vec<_> = chat.send(params("Hello!")).await.unwrap().collect().await;
+    assert_eq!(replies.len(), 2);
+    assert_eq!(chat.used_length(), 2 + 1 + 1);
+
+    chat.send(params("How are you?")).await.unwrap().collect::<Vec<_>>().await;
+    let inputs: Vec<_> = server.requests().iter().skip(1).map(|request| request["inputs"].clone()).collect();
+    assert_eq!(inputs, ["Hello!", "How are you?"]);
+    assert_eq!(chat.remaining_length(), 64 - 4 - 3 - 1);
+}
+
+#[tokio::test]
+async fn chat_rejects_turn_over_max_length() {
+    let server = MockServer::builder().start().await;
+    let mut chat = ChatSession::open(server.url(), 4, None).await.unwrap();
+    match chat.send(params("a turn that is far too long")).await {
+        Err(PetalsError::BudgetExceeded { requested: 8, remaining: 4 }) => {}
+        Err(e) => panic!("expected BudgetExceeded, got {e:?}"),
+        Ok(_) => panic!("expected BudgetExceeded"),
+    }
+    assert_eq!(server.requests().len(), 1);
+}
@@ -0,0 +1,57 @@
+#![allow(dead_code)]
+
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{copy_bidirectional, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use websocket_petals_api::{GenerateParams, GenerateParamsBuilder, RetryPolicy, SessionConfig, SessionConfigBuilder};
+
+// One new token per step, so the mock server's scripted steps map one-to-one to tokens.
+pub fn builder(inputs: &str) -> GenerateParamsBuilder {
+    GenerateParamsBuilder::new().inputs(inputs.to_owned()).max_new_tokens(1)
+}
+
+pub fn params(inputs: &str) -> GenerateParams {
+    builder(inputs).build().unwrap()
+}
+
+pub fn config(max_length: u32) -> SessionConfig {
+    SessionConfigBuilder::new(max_length).build()
+}
+
+pub fn no_retries(max_length: u32) -> SessionConfigBuilder {
+    SessionConfigBuilder::new(max_length).retry_policy(RetryPolicy::none())
+}
+
+// Minimal HTTP CONNECT proxy: records each request head, then tunnels bytes to the target.
+// Any `*.test` host resolves to the loopback interface, so tests can use names that are
+// unreachable without going through the proxy.
+pub async fn http_proxy(status: &'static str) -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let heads = Arc::new(Mutex::new(Vec::new()));
+    let recorded = heads.clone();
+    tokio::spawn(async move {
+        while let Ok((mut client, _)) = listener.accept().await {
+            let recorded = recorded.clone();
+            tokio::spawn(async move {
+                let mut head = Vec::new();
+                while !head.ends_with(b"\r\n\r\n") {
+                    head.push(client.read_u8().await.unwrap());
+                }
+                let head = String::from_utf8(head).unwrap();
+                let target = head.split_whitespace().nth(1).unwrap().to_owned();
+                let (host, port) = target.rsplit_once(':').unwrap();
+                let host = if host.ends_with(".test") { "127.0.0.1" } else { host };
+                let target = format!("{host}:{port}");
+                recorded.lock().unwrap().push(head);
+                client.write_all(format!("HTTP/1.1 {status}\r\n\r\n").as_bytes()).await.unwrap();
+                if status.starts_with('2') {
+                    let mut upstream = TcpStream::connect(target).await.unwrap();
+                    let _ = copy_bidirectional(&mut client, &mut upstream).await;
+                }
+            });
+        }
+    });
+    (addr, heads)
+}
@@ -0,0 +1,104 @@
+use std::sync::{Arc, Mutex};
+
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::accept_hdr_async;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::http::HeaderMap;
+use tokio_tungstenite::tungstenite::protocol::Message;
+use websocket_petals_api::protocol::ServerMessage;
+use websocket_petals_api::{
+    ConnectionConfigBuilder, Io, PetalsError, SessionConfigBuilder, TlsConnect, TlsConnector,
+};
+
+// Accepts a single connection, records its handshake headers and acknowledges the session.
+#[allow(clippy::result_large_err)]
+async fn serve_once() -> (String, Arc<Mutex<HeaderMap>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let headers = Arc::new(Mutex::new(HeaderMap::new()));
+    let recorded = headers.clone();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let callback = |request: &Request, mut response: Response| {
+            *recorded.lock().unwrap() = request.headers().clone();
+            if let Some(protocols) = request.headers().get("Sec-WebSocket-Protocol") {
+                let first = protocols.to_str().unwrap().split(',').next().unwrap().trim().parse().unwrap();
+                response.headers_mut().insert("Sec-WebSocket-Protocol", first);
+            }
+            Ok(response)
+        };
+        let mut ws_stream = accept_hdr_async(stream, callback).await.unwrap();
+        ws_stream.next().await.unwrap().unwrap();
+        let ack = ServerMessage::OpenAck.encode().unwrap();
+        ws_stream.send(Message::Text(ack.into())).await.unwrap();
+        while ws_stream.next().await.is_some() {}
+    });
+    (addr.to_string(), headers)
+}
+
+#[tokio::test]
+async fn sends_custom_headers_and_subprotocols() {
+    let (addr, headers) = serve_once().await;
+    let connection = ConnectionConfigBuilder::new(&format!("ws://{addr}/api/v2/generate"))
+        .bearer_token("secret")
+        .header("X-Api-Key", "42")
+        .subprotocol("petals.v2")
+        .subprotocol("petals.v1")
+        .build();
+    connection.open(&SessionConfigBuilder::new(512).build()).await.unwrap();
+
+    let headers = headers.lock().unwrap();
+    assert_eq!(headers["Authorization"], "Bearer secret");
+    assert_eq!(headers["X-Api-Key"], "42");
+    assert_eq!(headers["Sec-WebSocket-Protocol"], "petals.v2, petals.v1");
+}
+
+// Stands in for a rustls/native-tls wrapper: records the server name and skips encryption.
+struct RecordingTls(Arc<Mutex<Option<String>>>);
+
+impl TlsConnector for RecordingTls {
+    fn connect(&self, server_name: String, stream: TcpStream) -> TlsConnect<'_> {
+        *self.0.lock().unwrap() = Some(server_name);
+        Box::pin(async move { Ok(Box::new(stream) as Box<dyn Io>) })
+    }
+}
+
+#[tokio::test]
+async fn wss_uses_tls_connector_with_server_name_override() {
+    let (addr, headers) = serve_once().await;
+    let server_name = Arc::new(Mutex::new(None));
+    let connection = ConnectionConfigBuilder::new(&format!("wss://{addr}/api/v2/generate"))
+        .tls_connector(RecordingTls(server_name.clone()))
+        .server_name("gateway.internal")
+        .build();
+    connection.open(&SessionConfigBuilder::new(512).build()).await.unwrap();
+
+    assert_eq!(server_name.lock().unwrap().as_deref(), Some("gateway.internal"));
+    assert_eq!(headers.lock().unwrap()["Host"], addr.as_str());
+}
+
+#[cfg(not(any(feature = "rustls", feature = "native-tls")))]
+#[tokio::test]
+async fn wss_without_tls_feature_fails() {
+    use tokio_tungstenite::tungstenite::{error::UrlError, Error};
+
+    let (addr, _) = serve_once().await;
+    let connection = ConnectionConfigBuilder::new(&format!("wss://{addr}/api/v2/generate")).build();
+    assert!(matches!(
+        connection.connect().await,
+        Err(PetalsError::TungsteniteError(Error::Url(UrlError::TlsFeatureNotEnabled)))
+    ));
+}
+
+#[cfg(any(feature = "rustls", feature = "native-tls"))]
+#[tokio::test]
+async fn wss_uses_built_in_tls_by_default() {
+    use tokio_tungstenite::tungstenite::Error;
+
+    // The plain WebSocket server cannot complete a TLS handshake, so reaching it proves TLS was attempted.
+    let (addr, headers) = serve_once().await;
+    let connection = ConnectionConfigBuilder::new(&format!("wss://{addr}/api/v2/generate")).build();
+    assert!(matches!(connection.connect().await, Err(PetalsError::TungsteniteError(Error::Io(_)))));
+    assert!(headers.lock().unwrap().is_empty());
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use common::params;
+use futures_util::StreamExt;
+use websocket_petals_api::testing::{MockServer, Reply};
+use websocket_petals_api::{InferenceSession, PetalsError};
+
+#[tokio::test]
+async fn generate_streams_steps_until_stop() {
+    let server = MockServer::builder().tokens(&["Hello", ",", " world"]).start().await;
+    let mut session = InferenceSession::open(server.url(), 512, None).await.unwrap();
+    let steps: Vec<_> = session.generate(params("Hi")).await.unwrap().collect().await;
+    let outputs: Vec<_> = steps.into_iter().map(|step| step.unwrap().outputs).collect();
+    assert_eq!(outputs, ["Hello", ",", " world"]);
+}
+
+#[tokio::test]
+async fn generate_text_concatenates_steps() {
+    let server = MockServer::builder().tokens(&["Hello", ",", " world"]).tokens(&["!"]).start().await;
+    let mut session = InferenceSession::open(server.url(), 512, None).await.unwrap();
+    assert_eq!(session.generate_text(params("Hi")).await.unwrap(), "Hello, world");
+    assert_eq!(session.generate_text(params("")).await.unwrap(), "!");
+}
+
+#[tokio::test]
+async fn generate_surfaces_step_traceback() {
+    let server = MockServer::builder().generate(vec![Reply::step("a"), Reply::error("Traceback: boom")]).start().await;
+    let mut session = InferenceSession::open(server.url(), 512, None).await.unwrap();
+    match session.generate_text(params("Hi")).await {
+        Err(PetalsError::ApiError { traceback }) => assert_eq!(traceback, "Traceback: boom"),
+        other => panic!("expected ApiError, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn generate_reports_disconnect() {
+    let server = MockServer::builder().generate(vec![Reply::step("a"), Reply::Disconnect]).start().await;
+    let mut session = InferenceSession::open(server.url(), 512, None).await.unwrap();
+    assert!(session.generate_text(params("Hi")).await.is_err());
+}
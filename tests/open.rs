@@ -0,0 +1,63 @@
+use serde_json::json;
+use tokio_tungstenite::tungstenite::protocol::Message;
+use websocket_petals_api::testing::{MockServer, Reply};
+use websocket_petals_api::{InferenceSession, Model, OpenInferenceSessionError};
+
+async fn open_with_reply(reply: &'static str) -> Result<InferenceSession, OpenInferenceSessionError> {
+    let server = MockServer::builder().open(vec![Reply::Raw(Message::Text(reply.into()))]).start().await;
+    InferenceSession::open(server.url(), 512, None).await
+}
+
+#[tokio::test]
+async fn open_sends_session_request() {
+    let server = MockServer::builder().start().await;
+    InferenceSession::open(server.url(), 512, Some(Model::StableBeluga2)).await.unwrap();
+    assert_eq!(
+        server.requests(),
+        vec![json!({ "type": "open_inference_session", "max_length": 512, "model": "stabilityai/StableBeluga2" })]
+    );
+}
+
+#[tokio::test]
+async fn open_reports_boolean_failure() {
+    match open_with_reply(r#"{"ok": false, "traceback": "Traceback: out of memory"}"#).await {
+        Err(OpenInferenceSessionError::ApiError { traceback }) => assert_eq!(traceback, "Traceback: out of memory"),
+        other => panic!("expected ApiError, got {:?}", other.err()),
+    }
+}
+
+#[tokio::test]
+async fn open_reports_failure_without_traceback() {
+    match open_with_reply(r#"{"ok": false}"#).await {
+        Err(OpenInferenceSessionError::ApiError { traceback }) => assert!(traceback.is_empty()),
+        other => panic!("expected ApiError, got {:?}", other.err()),
+    }
+}
+
+#[tokio::test]
+async fn open_rejects_reply_without_ok() {
+    match open_with_reply(r#"{"traceback": "?"}"#).await {
+        Err(OpenInferenceSessionError::MissingField("ok")) => {}
+        other => panic!("expected MissingField, got {:?}", other.err()),
+    }
+}
+
+#[tokio::test]
+async fn open_reports_disconnect() {
+    let server = MockServer::builder().open(vec![Reply::Disconnect]).start().await;
+    assert!(InferenceSession::open(server.url(), 512, None).await.is_err());
+}
+
+#[tokio::test]
+async fn open_sends_custom_model_repo_id() {
+    let server = MockServer::builder().start().await;
+    let model = Model::Custom("petals-team/StableBeluga2-70b".to_owned());
+    InferenceSession::open(server.url(), 512, Some(model)).await.unwrap();
+    assert_eq!(server.requests()[0]["model"], "petals-team/StableBeluga2-70b");
+}
+
+#[test]
+fn model_deserializes_known_and_unknown_repo_ids() {
+    let models: Vec<Model> = serde_json::from_str(r#"["bigscience/bloomz", "my-org/private-model"]"#).unwrap();
+    assert_eq!(models, [Model::Bloomz, Model::Custom("my-org/private-model".to_owned())]);
+}
@@ -0,0 +1,76 @@
+use websocket_petals_api::{
+    GenerateParams, GenerateParamsBuilder, GenerateRequest, Model, ParamsError, RequestType, SamplingConfig,
+};
+
+fn builder() -> GenerateParamsBuilder {
+    GenerateParamsBuilder::new().max_new_tokens(1)
+}
+
+#[test]
+fn requires_a_length() {
+    assert_eq!(GenerateParamsBuilder::new().build().err(), Some(ParamsError::MissingLength));
+}
+
+#[test]
+fn rejects_out_of_range_sampling_params() {
+    assert_eq!(
+        builder().do_sample(true).temperature(0.0).build().err(),
+        Some(ParamsError::InvalidTemperature(0.0))
+    );
+    assert!(builder().do_sample(false).temperature(0.0).build().is_ok());
+    assert!(matches!(
+        builder().do_sample(true).temperature(f32::NAN).build(),
+        Err(ParamsError::InvalidTemperature(temperature)) if temperature.is_nan()
+    ));
+    assert_eq!(builder().top_p(0.0).build().err(), Some(ParamsError::InvalidTopP(0.0)));
+    assert_eq!(builder().top_p(1.5).build().err(), Some(ParamsError::InvalidTopP(1.5)));
+    assert!(matches!(builder().top_p(f32::NAN).build(), Err(ParamsError::InvalidTopP(_))));
+    assert!(builder().top_p(1.0).build().is_ok());
+    assert_eq!(builder().top_k(0).build().err(), Some(ParamsError::InvalidTopK));
+    assert_eq!(
+        builder().stop_sequence(String::new()).build().err(),
+        Some(ParamsError::EmptyStopSequence)
+    );
+}
+
+#[test]
+fn rejects_max_length_over_session_max_length() {
+    assert_eq!(
+        builder().max_length(1024).build_for_session(512).err(),
+        Some(ParamsError::MaxLengthExceeded { max_length: 1024, session_max_length: 512 })
+    );
+    assert!(builder().max_length(512).build_for_session(512).is_ok());
+}
+
+#[test]
+fn presets_round_trip_through_sampling_config() {
+    let config = builder().precise().sampling_config();
+    assert_eq!(config, SamplingConfig::precise());
+
+    let json = serde_json::to_string(&SamplingConfig::greedy()).unwrap();
+    assert_eq!(json, r#"{"do_sample":false}"#);
+    let loaded: SamplingConfig = serde_json::from_str(r#"{"do_sample":true,"temperature":0.5}"#).unwrap();
+    let config = builder().creative().sampling(loaded.clone()).sampling_config();
+    assert_eq!(config, loaded);
+}
+
+#[test]
+fn presets_pass_validation() {
+    for config in [SamplingConfig::greedy(), SamplingConfig::balanced(), SamplingConfig::creative(), SamplingConfig::precise()] {
+        assert!(builder().sampling(config).build().is_ok());
+    }
+}
+
+#[test]
+fn params_and_requests_round_trip_through_serde() {
+    let params = builder().inputs("Hi".to_owned()).model(Model::Bloomz).balanced().build().unwrap();
+    let json = serde_json::to_string(&params).unwrap();
+    assert_eq!(serde_json::from_str::<GenerateParams>(&json).unwrap(), params.clone());
+
+    let request = GenerateRequest::from(params);
+    assert_eq!(request.request_type, RequestType::Generate);
+    let wire = serde_json::to_value(&request).unwrap();
+    assert_eq!(wire["type"], "generate");
+    assert_eq!(wire["model"], "bigscience/bloomz");
+    assert_eq!(serde_json::from_value::<GenerateRequest>(wire).unwrap(), request);
+}
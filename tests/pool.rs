@@ -0,0 +1,144 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{config, params};
+use futures_util::StreamExt;
+
+use websocket_petals_api::testing::{MockServer, Reply};
+use websocket_petals_api::{PetalsError, SessionConfigBuilder, SessionPool, TimeoutKind, TokenCounter};
+
+struct Words;
+
+impl TokenCounter for Words {
+    fn count(&self, text: &str) -> u32 {
+        text.split_whitespace().count() as u32
+    }
+}
+
+#[tokio::test]
+async fn reuses_idle_sessions_per_key() {
+    let server = MockServer::builder().tokens(&["a"]).tokens(&["b"]).start().await;
+    let pool = SessionPool::builder().share_context(true).build();
+
+    let mut session = pool.get(&server.url(), &config(512)).await.unwrap();
+    assert_eq!(session.generate_text(params("1")).await.unwrap(), "a");
+    drop(session);
+    assert_eq!(pool.idle_sessions(), 1);
+
+    let mut session = pool.get(&server.url(), &config(512)).await.unwrap();
+    assert_eq!(session.generate_text(params("2")).await.unwrap(), "b");
+    assert_eq!(server.connections(), 1);
+
+    let _other = pool.get(&server.url(), &config(1024)).await.unwrap();
+    assert_eq!(server.connections(), 2);
+}
+
+#[tokio::test]
+async fn caps_checked_out_sessions() {
+    let server = MockServer::builder().start().await;
+    let pool = SessionPool::builder().max_sessions(1).build();
+
+    let session = pool.get(&server.url(), &config(512)).await.unwrap();
+    let waiting = tokio::time::timeout(Duration::from_millis(50), pool.get(&server.url(), &config(512))).await;
+    assert!(waiting.is_err());
+
+    drop(session);
+    pool.get(&server.url(), &config(512)).await.unwrap();
+    assert_eq!(server.connections(), 1);
+}
+
+#[tokio::test]
+async fn reopens_when_idle_session_was_closed() {
+    let server = MockServer::builder().generate(vec![Reply::last_step("a"), Reply::Disconnect]).start().await;
+    let pool = SessionPool::builder().share_context(true).build();
+
+    let mut session = pool.get(&server.url(), &config(512)).await.unwrap();
+    session.generate_text(params("1")).await.unwrap();
+    drop(session);
+    assert_eq!(pool.idle_sessions(), 1);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    pool.get(&server.url(), &config(512)).await.unwrap();
+    assert_eq!(server.connections(), 2);
+}
+
+#[tokio::test]
+async fn evicts_stale_and_discarded_sessions() {
+    let server = MockServer::builder().start().await;
+    let pool = SessionPool::builder().idle_ttl(Duration::from_millis(20)).build();
+
+    drop(pool.get(&server.url(), &config(512)).await.unwrap());
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    pool.evict_stale();
+    assert_eq!(pool.idle_sessions(), 0);
+
+    pool.get(&server.url(), &config(512)).await.unwrap().discard();
+    assert_eq!(pool.idle_sessions(), 0);
+    assert_eq!(server.connections(), 2);
+}
+
+#[tokio::test]
+async fn does_not_share_context_by_default() {
+    let server = MockServer::builder().start().await;
+    let pool = SessionPool::default();
+
+    drop(pool.get(&server.url(), &config(512)).await.unwrap());
+    assert_eq!(pool.idle_sessions(), 1);
+
+    let mut session = pool.get(&server.url(), &config(512)).await.unwrap();
+    session.generate_text(params("1")).await.unwrap();
+    drop(session);
+    assert_eq!(pool.idle_sessions(), 0);
+    assert_eq!(server.connections(), 1);
+}
+
+#[tokio::test]
+async fn retires_unfinished_failed_and_exhausted_sessions() {
+    let server = MockServer::builder()
+        .tokens(&["a", "b", "c"])
+        .generate(vec![Reply::error("out of memory")])
+        .start()
+        .await;
+    let pool = SessionPool::builder().share_context(true).min_remaining_length(8).build();
+
+    let mut session = pool.get(&server.url(), &config(512)).await.unwrap();
+    let mut steps = session.generate(params("1")).await.unwrap();
+    steps.next().await.unwrap().unwrap();
+    drop(steps);
+    drop(session);
+    assert_eq!(pool.idle_sessions(), 0);
+
+    let mut session = pool.get(&server.url(), &config(512)).await.unwrap();
+    assert!(session.generate_text(params("1")).await.is_err());
+    drop(session);
+    assert_eq!(pool.idle_sessions(), 0);
+
+    let mut session = pool.get(&server.url(), &config(16)).await.unwrap();
+    session.generate_text(params("a prompt that takes up ten tokens or so.")).await.unwrap();
+    drop(session);
+    assert_eq!(pool.idle_sessions(), 0);
+    assert_eq!(server.connections(), 3);
+}
+
+#[tokio::test]
+async fn idle_sessions_take_the_requested_config() {
+    let server = MockServer::builder()
+        .generate(vec![Reply::Delay(Duration::from_secs(5)), Reply::last_step("late")])
+        .start()
+        .await;
+    let pool = SessionPool::default();
+    drop(pool.get(&server.url(), &config(512)).await.unwrap());
+
+    let strict = SessionConfigBuilder::new(512)
+        .first_token_timeout(Duration::from_millis(100))
+        .token_counter(Words)
+        .build();
+    let mut session = pool.get(&server.url(), &strict).await.unwrap();
+    assert_eq!(server.connections(), 1);
+    assert_eq!(session.token_counter().count("one two three"), 3);
+    assert!(matches!(
+        session.generate_text(params("")).await,
+        Err(PetalsError::Timeout(TimeoutKind::FirstToken))
+    ));
+}
@@ -0,0 +1,62 @@
+use websocket_petals_api::protocol::{ClientMessage, OpenSessionRequest, RequestType, Response, ServerMessage};
+use websocket_petals_api::{GenerateParamsBuilder, GenerateRequest, Model, PetalsError};
+
+#[test]
+fn decodes_server_messages() {
+    assert_eq!(ServerMessage::decode(r#"{"ok": true}"#).unwrap(), ServerMessage::OpenAck);
+    assert_eq!(
+        ServerMessage::decode(r#"{"ok": true, "outputs": "Hi", "stop": false}"#).unwrap(),
+        ServerMessage::Step(Response { ok: true, outputs: "Hi".to_owned(), stop: false })
+    );
+    assert_eq!(
+        ServerMessage::decode(r#"{"ok": false, "traceback": "boom"}"#).unwrap(),
+        ServerMessage::Error { traceback: "boom".to_owned() }
+    );
+    assert!(matches!(ServerMessage::decode(r#"{"outputs": "Hi"}"#), Err(PetalsError::MissingField("ok"))));
+    assert!(matches!(ServerMessage::decode("not json"), Err(PetalsError::JsonError(_))));
+}
+
+#[test]
+fn server_messages_round_trip() {
+    for message in [
+        ServerMessage::OpenAck,
+        ServerMessage::Step(Response { ok: true, outputs: " world".to_owned(), stop: true }),
+        ServerMessage::Error { traceback: "boom".to_owned() },
+    ] {
+        assert_eq!(ServerMessage::decode(&message.encode().unwrap()).unwrap(), message);
+    }
+}
+
+#[test]
+fn client_messages_round_trip() {
+    let open = ClientMessage::OpenInferenceSession(OpenSessionRequest {
+        request_type: RequestType::OpenInferenceSession,
+        max_length: 512,
+        model: Some(Model::StableBeluga2),
+    });
+    let params = GenerateParamsBuilder::new().inputs("Hi".to_owned()).max_new_tokens(1).build().unwrap();
+    let generate = ClientMessage::Generate(GenerateRequest::from(params));
+    for message in [open, generate] {
+        assert_eq!(ClientMessage::decode(&message.encode().unwrap()).unwrap(), message);
+    }
+    assert!(matches!(ClientMessage::decode(r#"{"max_length": 1}"#), Err(PetalsError::MissingField("type"))));
+}
+
+#[test]
+fn client_message_variant_decides_the_type() {
+    let mismatched = OpenSessionRequest {
+        request_type: RequestType::Generate,
+        max_length: 512,
+        model: None,
+    };
+    let text = ClientMessage::OpenInferenceSession(mismatched.clone()).encode().unwrap();
+    let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(value["type"], "open_inference_session");
+    assert_eq!(
+        ClientMessage::decode(&text).unwrap(),
+        ClientMessage::OpenInferenceSession(OpenSessionRequest {
+            request_type: RequestType::OpenInferenceSession,
+            ..mismatched
+        })
+    );
+}
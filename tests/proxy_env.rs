@@ -0,0 +1,34 @@
+// Kept in its own test binary: it changes process-wide environment variables.
+mod common;
+
+use common::http_proxy;
+use websocket_petals_api::testing::MockServer;
+use tokio_tungstenite::tungstenite::{error::UrlError, Error};
+use websocket_petals_api::{ConnectionConfigBuilder, PetalsError, RetryPolicy, SessionConfigBuilder};
+
+#[tokio::test]
+async fn proxy_from_env_is_opt_in_and_skips_loopback() {
+    let server = MockServer::builder().start().await;
+    let (proxy_addr, heads) = http_proxy("200 Connection established").await;
+    let config = SessionConfigBuilder::new(512).retry_policy(RetryPolicy::none()).build();
+    let remote_url = format!("ws://petals.test:{}/api/v2/generate", server.addr().port());
+    std::env::set_var("ALL_PROXY", format!("http://{proxy_addr}"));
+
+    ConnectionConfigBuilder::new(&remote_url).proxy_from_env().build().open(&config).await.unwrap();
+    assert_eq!(heads.lock().unwrap().len(), 1);
+
+    ConnectionConfigBuilder::new(&server.url()).proxy_from_env().build().open(&config).await.unwrap();
+    let unset = ConnectionConfigBuilder::new(&remote_url).build().open(&config).await;
+    assert!(unset.is_err());
+    assert_eq!(heads.lock().unwrap().len(), 1);
+
+    // An unusable value fails the connection instead of silently dialling directly.
+    std::env::set_var("ALL_PROXY", "https://proxy.internal:443");
+    let rejected = ConnectionConfigBuilder::new(&remote_url).proxy_from_env().build().connect().await;
+    std::env::remove_var("ALL_PROXY");
+    assert!(matches!(
+        rejected,
+        Err(PetalsError::TungsteniteError(Error::Url(UrlError::UnsupportedUrlScheme)))
+    ));
+    assert_eq!(server.connections(), 2);
+}
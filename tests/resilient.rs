@@ -0,0 +1,90 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{builder, no_retries, params};
+use futures_util::StreamExt;
+use websocket_petals_api::testing::{MockServer, Reply};
+use websocket_petals_api::{PetalsError, ResilientSession, TimeoutKind};
+
+#[tokio::test]
+async fn resumes_generation_after_disconnect() {
+    let server = MockServer::builder()
+        .tokens(&["Hello"])
+        .generate(vec![Reply::step(" big"), Reply::Disconnect])
+        .tokens(&[" world"])
+        .start()
+        .await;
+    let mut session = ResilientSession::open(&server.url(), 512, None).await.unwrap();
+    session.generate(params("Hi.")).await.unwrap().collect::<Vec<_>>().await;
+
+    let steps: Vec<_> = session.generate(params(" Again:")).await.unwrap().collect().await;
+    let outputs: Vec<_> = steps.into_iter().map(|step| step.unwrap().outputs).collect();
+    assert_eq!(outputs, [" big", " world"]);
+    assert_eq!(server.connections(), 2);
+
+    let requests = server.requests();
+    let types: Vec<_> = requests.iter().map(|request| request["type"].as_str().unwrap()).collect();
+    assert_eq!(types, ["open_inference_session", "generate", "generate", "open_inference_session", "generate"]);
+    assert_eq!(requests[4]["inputs"], "Hi.Hello Again: big");
+    assert_eq!(session.history(), "Hi.Hello Again: big world");
+}
+
+#[tokio::test]
+async fn gives_up_after_max_reconnects() {
+    let server = MockServer::builder()
+        .generate(vec![Reply::Disconnect])
+        .generate(vec![Reply::Disconnect])
+        .start()
+        .await;
+    let mut session = ResilientSession::open(&server.url(), 512, None).await.unwrap().max_reconnects(1);
+    let steps: Vec<_> = session.generate(params("Hi")).await.unwrap().collect().await;
+    assert!(matches!(steps.as_slice(), [Err(e)] if e.is_disconnect()));
+    assert_eq!(server.connections(), 2);
+}
+
+#[tokio::test]
+async fn rejected_inputs_stay_out_of_history() {
+    let server = MockServer::builder().start().await;
+    let mut session = ResilientSession::open(&server.url(), 8, None).await.unwrap();
+    assert!(matches!(
+        session.generate(params("a prompt that is far too long")).await.err(),
+        Some(PetalsError::BudgetExceeded { .. })
+    ));
+    assert_eq!(session.history(), "");
+}
+
+#[tokio::test]
+async fn reconnects_apply_session_config_timeouts() {
+    let server = MockServer::builder()
+        .generate(vec![Reply::Disconnect])
+        .generate(vec![Reply::Delay(Duration::from_secs(5)), Reply::last_step("late")])
+        .start()
+        .await;
+    let config = no_retries(512).first_token_timeout(Duration::from_millis(100)).build();
+    let mut session = ResilientSession::open_with(&server.url(), config).await.unwrap();
+    let steps: Vec<_> = session.generate(params("Hi")).await.unwrap().collect().await;
+    assert!(matches!(steps.as_slice(), [Err(PetalsError::Timeout(TimeoutKind::FirstToken))]));
+    assert_eq!(server.connections(), 2);
+}
+
+#[tokio::test]
+async fn history_keeps_text_past_a_stop_sequence() {
+    let server = MockServer::builder()
+        .tokens(&["a", "b", "#", "c", "d"])
+        .generate(vec![Reply::step(" x"), Reply::Disconnect])
+        .tokens(&[" y"])
+        .start()
+        .await;
+    let mut session = ResilientSession::open(&server.url(), 512, None).await.unwrap();
+    let stop = builder("Hi").stop_sequences(vec!["#".to_owned()]).build().unwrap();
+    let steps: Vec<_> = session.generate(stop).await.unwrap().collect().await;
+    let outputs: Vec<_> = steps.into_iter().map(|step| step.unwrap().outputs).collect();
+    assert_eq!(outputs.concat(), "ab");
+
+    // The server cached "#cd" too, so the next request drains it and a replay includes it.
+    session.generate(params(" Again:")).await.unwrap().collect::<Vec<_>>().await;
+    let requests = server.requests();
+    assert_eq!(requests.last().unwrap()["inputs"], "Hiab#cd Again: x");
+    assert_eq!(session.history(), "Hiab#cd Again: x y");
+}
@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use websocket_petals_api::testing::{MockServer, Reply};
+use websocket_petals_api::{InferenceSession, PetalsError, RetryPolicyBuilder, SessionConfigBuilder, TimeoutKind};
+
+fn fast_retries() -> RetryPolicyBuilder {
+    RetryPolicyBuilder::new().initial_backoff(Duration::from_millis(1)).jitter(false)
+}
+
+#[tokio::test]
+async fn open_with_retries_rejected_sessions() {
+    let server = MockServer::builder()
+        .open(vec![Reply::error("no servers available")])
+        .open(vec![Reply::Disconnect])
+        .start()
+        .await;
+    let config = SessionConfigBuilder::new(512).retry_policy(fast_retries().max_attempts(3).build()).build();
+    InferenceSession::open_with(server.url(), &config).await.unwrap();
+    assert_eq!(server.connections(), 3);
+}
+
+#[tokio::test]
+async fn open_with_stops_on_non_retryable_errors() {
+    let server = MockServer::builder().open(vec![Reply::error("bad model")]).start().await;
+    let policy = fast_retries().retry_if(PetalsError::is_disconnect).build();
+    let config = SessionConfigBuilder::new(512).retry_policy(policy).build();
+    assert!(matches!(
+        InferenceSession::open_with(server.url(), &config).await,
+        Err(PetalsError::ApiError { .. })
+    ));
+    assert_eq!(server.connections(), 1);
+}
+
+#[test]
+fn backoff_grows_exponentially_up_to_max() {
+    let policy = RetryPolicyBuilder::new()
+        .initial_backoff(Duration::from_millis(100))
+        .max_backoff(Duration::from_millis(300))
+        .jitter(false)
+        .build();
+    let backoffs: Vec<_> = (0..3).map(|retry| policy.backoff(retry).as_millis()).collect();
+    assert_eq!(backoffs, [100, 200, 300]);
+
+    let jittered = RetryPolicyBuilder::new().initial_backoff(Duration::from_millis(100)).build().backoff(0);
+    assert!(jittered >= Duration::from_millis(50) && jittered <= Duration::from_millis(100));
+}
+
+#[test]
+fn backoff_stays_at_max_for_late_retries() {
+    let policy = RetryPolicyBuilder::new()
+        .max_attempts(u32::MAX)
+        .deadline(Duration::from_secs(3600))
+        .jitter(false)
+        .build();
+    assert_eq!(policy.backoff(200), Duration::from_secs(10));
+    assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(10));
+    assert!(RetryPolicyBuilder::new().build().backoff(200) <= Duration::from_secs(10));
+
+    let zero = RetryPolicyBuilder::new().initial_backoff(Duration::ZERO).jitter(false).build();
+    assert_eq!(zero.backoff(200), Duration::ZERO);
+}
+
+#[test]
+fn multiplier_below_one_or_not_finite_is_clamped() {
+    for multiplier in [-2.0, 0.5, f64::NAN, f64::INFINITY] {
+        let policy = RetryPolicyBuilder::new().multiplier(multiplier).jitter(false).build();
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(200), Duration::from_millis(500));
+    }
+}
+
+#[tokio::test]
+async fn deadline_bounds_each_attempt() {
+    // Accepts TCP connections into the backlog but never answers the WebSocket handshake.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}/api/v2/generate", listener.local_addr().unwrap());
+    let policy = fast_retries().deadline(Duration::from_millis(100)).build();
+    let config = SessionConfigBuilder::new(512).retry_policy(policy).build();
+
+    let result = tokio::time::timeout(Duration::from_secs(2), InferenceSession::open_with(url, &config)).await;
+    assert!(matches!(result, Ok(Err(PetalsError::Timeout(TimeoutKind::Deadline)))));
+}
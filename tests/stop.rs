@@ -0,0 +1,39 @@
+mod common;
+
+use common::builder;
+use futures_util::StreamExt;
+use websocket_petals_api::testing::MockServer;
+use websocket_petals_api::{GenerateParams, InferenceSession};
+
+fn params(stop_sequences: &[&str]) -> GenerateParams {
+    builder("")
+        .stop_sequences(stop_sequences.iter().map(|stop| stop.to_string()).collect())
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn stops_on_any_sequence_split_across_steps() {
+    let server = MockServer::builder()
+        .tokens(&["Sure", ".\n#", "## Hu", "man: next", " turn"])
+        .tokens(&["ok"])
+        .start()
+        .await;
+    let mut session = InferenceSession::open(server.url(), 512, None).await.unwrap();
+
+    let steps: Vec<_> = session.generate(params(&["</s>", "\n### Human:"])).await.unwrap().collect().await;
+    let steps: Vec<_> = steps.into_iter().map(Result::unwrap).collect();
+    let outputs: Vec<_> = steps.iter().map(|step| step.outputs.as_str()).collect();
+    assert_eq!(outputs, ["Sure", ".", "", ""]);
+    assert!(steps.last().unwrap().stop);
+    assert_eq!(server.requests()[1]["stop_sequence"], "</s>");
+
+    assert_eq!(session.generate_text(params(&[])).await.unwrap(), "ok");
+}
+
+#[tokio::test]
+async fn releases_held_back_text_without_match() {
+    let server = MockServer::builder().tokens(&["a <", "/b", ">"]).start().await;
+    let mut session = InferenceSession::open(server.url(), 512, None).await.unwrap();
+    assert_eq!(session.generate_text(params(&["</s>"])).await.unwrap(), "a </b>");
+}
@@ -0,0 +1,45 @@
+use websocket_petals_api::{ChatMessage, ChatTemplate, Model};
+
+fn conversation() -> Vec<ChatMessage> {
+    vec![
+        ChatMessage::system("You are terse."),
+        ChatMessage::user("Hi"),
+        ChatMessage::assistant("Hello."),
+        ChatMessage::user("Bye"),
+    ]
+}
+
+#[test]
+fn llama2_chat_template() {
+    assert_eq!(
+        Model::Llama2_70bChatHf.format(&conversation()),
+        "<s>[INST] <<SYS>>\nYou are terse.\n<</SYS>>\n\nHi [/INST] Hello. </s><s>[INST] Bye [/INST]"
+    );
+    assert_eq!(Model::Llama2_70bChatHf.stop_sequence(), "</s>");
+}
+
+#[test]
+fn stable_beluga_template() {
+    assert_eq!(
+        Model::StableBeluga2.format(&conversation()),
+        "### System:\nYou are terse.\n\n### User:\nHi\n\n### Assistant:\nHello.\n\n### User:\nBye\n\n### Assistant:\n"
+    );
+}
+
+#[test]
+fn guanaco_template() {
+    assert_eq!(
+        Model::Guanaco65b.format(&conversation()),
+        "You are terse.\n### Human: Hi\n### Assistant: Hello.\n### Human: Bye\n### Assistant:"
+    );
+}
+
+#[test]
+fn custom_models_use_plain_transcript() {
+    let model = Model::Custom("my-org/private-model".to_owned());
+    assert_eq!(
+        model.format(&conversation()),
+        "You are terse.\n\nHuman: Hi\nAssistant: Hello.\nHuman: Bye\nAssistant:"
+    );
+    assert_eq!(model.stop_sequence(), "\nHuman:");
+}
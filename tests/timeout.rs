@@ -0,0 +1,52 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{no_retries, params};
+use futures_util::StreamExt;
+use tokio::net::TcpListener;
+use websocket_petals_api::testing::{MockServer, Reply};
+use websocket_petals_api::{InferenceSession, PetalsError, TimeoutKind};
+
+const STALL: Duration = Duration::from_secs(5);
+const TIMEOUT: Duration = Duration::from_millis(100);
+
+#[tokio::test]
+async fn connect_timeout() {
+    // The listener never accepts, so the WebSocket upgrade never gets an answer.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}/api/v2/generate", listener.local_addr().unwrap());
+    let config = no_retries(512).connect_timeout(TIMEOUT).build();
+    assert!(matches!(
+        InferenceSession::open_with(url, &config).await,
+        Err(PetalsError::Timeout(TimeoutKind::Connect))
+    ));
+}
+
+#[tokio::test]
+async fn handshake_timeout() {
+    let server = MockServer::builder().open(vec![Reply::Delay(STALL), Reply::Ok]).start().await;
+    let config = no_retries(512).handshake_timeout(TIMEOUT).build();
+    assert!(matches!(
+        InferenceSession::open_with(server.url(), &config).await,
+        Err(PetalsError::Timeout(TimeoutKind::Handshake))
+    ));
+}
+
+#[tokio::test]
+async fn first_and_inter_token_timeouts() {
+    let server = MockServer::builder()
+        .generate(vec![Reply::Delay(STALL), Reply::last_step("late")])
+        .generate(vec![Reply::step("a"), Reply::Delay(STALL), Reply::last_step("late")])
+        .start()
+        .await;
+    let config = no_retries(512).first_token_timeout(TIMEOUT).inter_token_timeout(TIMEOUT).build();
+
+    let mut session = InferenceSession::open_with(server.url(), &config).await.unwrap();
+    let steps: Vec<_> = session.generate(params("")).await.unwrap().collect().await;
+    assert!(matches!(steps.as_slice(), [Err(PetalsError::Timeout(TimeoutKind::FirstToken))]));
+
+    let mut session = InferenceSession::open_with(server.url(), &config).await.unwrap();
+    let steps: Vec<_> = session.generate(params("")).await.unwrap().collect().await;
+    assert!(matches!(steps.as_slice(), [Ok(_), Err(PetalsError::Timeout(TimeoutKind::InterToken))]));
+}
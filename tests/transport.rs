@@ -0,0 +1,26 @@
+use futures_util::{SinkExt, StreamExt};
+use tokio::io::duplex;
+use tokio_tungstenite::tungstenite::protocol::Message;
+use tokio_tungstenite::{accept_async, client_async};
+use websocket_petals_api::protocol::{ClientMessage, Response, ServerMessage};
+use websocket_petals_api::{GenerateParamsBuilder, InferenceSession};
+
+#[tokio::test]
+async fn session_over_in_memory_duplex() {
+    let (client_io, server_io) = duplex(4096);
+    tokio::spawn(async move {
+        let mut ws_stream = accept_async(server_io).await.unwrap();
+        while let Some(Ok(Message::Text(text))) = ws_stream.next().await {
+            let reply = match ClientMessage::decode(&text).unwrap() {
+                ClientMessage::OpenInferenceSession(_) => ServerMessage::OpenAck,
+                _ => ServerMessage::Step(Response { ok: true, outputs: "duplex".to_owned(), stop: true }),
+            };
+            ws_stream.send(Message::Text(reply.encode().unwrap().into())).await.unwrap();
+        }
+    });
+
+    let (ws_stream, _) = client_async("ws://localhost/api/v2/generate", client_io).await.unwrap();
+    let mut session = InferenceSession::from_stream(ws_stream, 512, None).await.unwrap();
+    let params = GenerateParamsBuilder::new().max_new_tokens(1).build().unwrap();
+    assert_eq!(session.generate_text(params).await.unwrap(), "duplex");
+}